@@ -0,0 +1,125 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, ed25519_program, msg, program_error::ProgramError, pubkey::Pubkey,
+    sysvar,
+};
+
+use crate::error::FaucetError;
+
+// Layout of the Ed25519 program instruction data, see `solana_sdk::ed25519_instruction`
+const SIGNATURE_OFFSETS_START: usize = 2;
+const DATA_START: usize = SIGNATURE_OFFSETS_START + 14;
+const PUBLIC_KEY_SIZE: usize = 32;
+const SIGNATURE_SIZE: usize = 64;
+// Offsets pointing at this instruction index refer to the Ed25519 instruction itself
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// What the attester signs to let `recipient` claim `amount` from `faucet` once.
+///
+/// The signed message is the borsh encoding of the attestation.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Attestation {
+    pub faucet: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,       // each nonce is accepted once per faucet
+    pub expiry_slot: u64, // last slot the attestation is accepted at
+}
+
+impl Attestation {
+    pub fn message(&self) -> Vec<u8> {
+        borsh::to_vec(self).unwrap()
+    }
+}
+
+/// Ed25519 program instruction checking `signature` of `message` by `attester`.
+///
+/// Goes right before the `RequestTokens` instruction it attests.
+#[cfg(not(target_os = "solana"))]
+pub fn new_ed25519_instruction(
+    attester: &Pubkey,
+    signature: &[u8; SIGNATURE_SIZE],
+    message: &[u8],
+) -> solana_program::instruction::Instruction {
+    let public_key_offset = DATA_START;
+    let signature_offset = public_key_offset + PUBLIC_KEY_SIZE;
+    let message_offset = signature_offset + SIGNATURE_SIZE;
+
+    let mut data = Vec::with_capacity(message_offset + message.len());
+    data.extend_from_slice(&[1, 0]); // one signature, then padding
+    for offset in [
+        signature_offset as u16,
+        CURRENT_INSTRUCTION,
+        public_key_offset as u16,
+        CURRENT_INSTRUCTION,
+        message_offset as u16,
+        message.len() as u16,
+        CURRENT_INSTRUCTION,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(attester.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    solana_program::instruction::Instruction::new_with_bytes(ed25519_program::id(), &data, vec![])
+}
+
+// The attestation signed by `attester` in the Ed25519 instruction right before this one.
+//
+// The runtime already verified the signature, only what was signed and by whom is left.
+pub(crate) fn load_attestation(
+    instructions_sysvar: &AccountInfo,
+    attester: &Pubkey,
+) -> Result<Attestation, ProgramError> {
+    if !sysvar::instructions::check_id(instructions_sysvar.key) {
+        msg!("Attested claims need the instructions sysvar");
+        return Err(FaucetError::InvalidAttestation.into());
+    }
+
+    let instruction = sysvar::instructions::get_instruction_relative(-1, instructions_sysvar)
+        .map_err(|_| {
+            msg!("Attested claims need an Ed25519 instruction right before them");
+            FaucetError::InvalidAttestation
+        })?;
+    if instruction.program_id != ed25519_program::id() {
+        msg!("Instruction before the claim must be an Ed25519 signature check");
+        return Err(FaucetError::InvalidAttestation.into());
+    }
+
+    let (public_key, message) = signed_message(&instruction.data).ok_or_else(|| {
+        msg!("Ed25519 instruction must check exactly one self-contained signature");
+        FaucetError::InvalidAttestation
+    })?;
+    if public_key != attester.as_ref() {
+        msg!("Attestation must be signed by the attester {}", attester);
+        return Err(FaucetError::InvalidAttestation.into());
+    }
+
+    Attestation::try_from_slice(message).map_err(|_| {
+        msg!("Signed message is not an attestation");
+        FaucetError::InvalidAttestation.into()
+    })
+}
+
+// Public key and message of a single signature check whose data sits in the instruction
+fn signed_message(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    if data.first() != Some(&1) {
+        return None;
+    }
+
+    let offsets = data.get(SIGNATURE_OFFSETS_START..DATA_START)?;
+    let read = |index: usize| u16::from_le_bytes([offsets[2 * index], offsets[2 * index + 1]]);
+    let [signature_ix, public_key_offset, public_key_ix, message_offset, message_size, message_ix] =
+        [1, 2, 3, 4, 5, 6].map(read);
+    // The runtime checks data of other instructions, not the bytes read below
+    if [signature_ix, public_key_ix, message_ix] != [CURRENT_INSTRUCTION; 3] {
+        return None;
+    }
+
+    let public_key_offset = usize::from(public_key_offset);
+    let message_offset = usize::from(message_offset);
+    let public_key = data.get(public_key_offset..public_key_offset + PUBLIC_KEY_SIZE)?;
+    let message = data.get(message_offset..message_offset + usize::from(message_size))?;
+    Some((public_key, message))
+}
@@ -0,0 +1,231 @@
+use serde_json::{json, Value};
+use simple_token_faucet::instruction::{self, ClaimOptions, ConfigUpdate};
+use simple_token_faucet::rpc::{FaucetView, NewFaucet};
+use simple_token_faucet::state::FaucetMode;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, native_token::lamports_to_sol, pubkey::Pubkey,
+    signer::Signer,
+};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+
+use crate::{CliResult, Context};
+
+// Denylist updates per transaction, keeps bulk updates under the transaction size limit
+const DENYLIST_BATCH_SIZE: usize = 8;
+
+pub fn init(
+    context: &Context,
+    distribution_amount: u64,
+    cooldown_slots: u64,
+    allow_program_owned_recipients: bool,
+    mode: FaucetMode,
+    mint: Option<Pubkey>,
+) -> CliResult {
+    let (faucet, signature) = context.client.initialize(
+        &context.keypair,
+        &NewFaucet {
+            name: context.faucet_name.clone(),
+            distribution_amount,
+            cooldown_slots,
+            allow_program_owned_recipients,
+            mode,
+            mint,
+        },
+    )?;
+
+    Ok(json!({
+        "faucet": faucet.to_string(),
+        "admin": context.keypair.pubkey().to_string(),
+        "signature": signature.to_string(),
+    }))
+}
+
+pub fn request(context: &Context, recipient: Pubkey) -> CliResult {
+    let faucet_state = context.client.get_faucet_state(&context.faucet)?;
+    let signature = context.client.request_tokens(
+        &context.faucet,
+        &recipient,
+        &context.keypair,
+        &ClaimOptions::default(),
+    )?;
+
+    Ok(json!({
+        "recipient": recipient.to_string(),
+        "amount": faucet_state.distribution_amount,
+        "signature": signature.to_string(),
+    }))
+}
+
+pub fn replenish(context: &Context, amount: u64) -> CliResult {
+    let signature = context
+        .client
+        .replenish(&context.faucet, &context.keypair, amount)?;
+
+    Ok(json!({
+        "amount": amount,
+        "signature": signature.to_string(),
+    }))
+}
+
+pub fn show(context: &Context) -> CliResult {
+    let faucet = context.client.get_faucet(&context.faucet)?;
+    Ok(faucet_json(&faucet))
+}
+
+pub fn list(context: &Context) -> CliResult {
+    let faucets = context.client.get_faucets()?;
+    Ok(Value::Array(faucets.iter().map(faucet_json).collect()))
+}
+
+pub fn update_config(context: &Context, update: ConfigUpdate) -> CliResult {
+    let signature = context.send(&[instruction::update_config(
+        &context.program_id,
+        &context.faucet,
+        &context.keypair.pubkey(),
+        update,
+    )])?;
+
+    Ok(json!({ "signature": signature.to_string() }))
+}
+
+pub fn withdraw(context: &Context, amount: u64, destination: Pubkey) -> CliResult {
+    let signature = context.send(&[instruction::withdraw(
+        &context.program_id,
+        &context.faucet,
+        &context.keypair.pubkey(),
+        &destination,
+        amount,
+    )])?;
+
+    Ok(json!({
+        "amount": amount,
+        "destination": destination.to_string(),
+        "signature": signature.to_string(),
+    }))
+}
+
+pub fn close(context: &Context, destination: Pubkey) -> CliResult {
+    let faucet_state = context.client.get_faucet_state(&context.faucet)?;
+    let lamports = context.client.rpc_client().get_balance(&context.faucet)?;
+    let admin = context.keypair.pubkey();
+    let instructions = match faucet_state.mode {
+        // The vault tokens need a token account of the destination, created when missing
+        FaucetMode::TokenVault => {
+            let token_program = context.client.token_program(&faucet_state.mint)?;
+            vec![
+                create_associated_token_account_idempotent(
+                    &admin,
+                    &destination,
+                    &faucet_state.mint,
+                    &token_program,
+                ),
+                instruction::close_token_vault_faucet(
+                    &context.program_id,
+                    &context.faucet,
+                    &admin,
+                    &destination,
+                    &faucet_state.mint,
+                    &token_program,
+                ),
+            ]
+        }
+        FaucetMode::MintAuthority => vec![instruction::close_mint_authority_faucet(
+            &context.program_id,
+            &context.faucet,
+            &admin,
+            &destination,
+            &faucet_state.mint,
+            &context.client.token_program(&faucet_state.mint)?,
+        )],
+        FaucetMode::Native => vec![instruction::close_faucet(
+            &context.program_id,
+            &context.faucet,
+            &admin,
+            &destination,
+        )],
+    };
+    let signature = context.send(&instructions)?;
+
+    Ok(json!({
+        "reclaimed_lamports": lamports,
+        "destination": destination.to_string(),
+        "signature": signature.to_string(),
+    }))
+}
+
+// Add or remove wallets from the denylist, batching several wallets per transaction
+pub fn update_denylist(context: &Context, wallets: &[Pubkey], deny: bool) -> CliResult {
+    let admin = context.keypair.pubkey();
+    let mut signatures = Vec::new();
+
+    for batch in wallets.chunks(DENYLIST_BATCH_SIZE) {
+        let instructions: Vec<Instruction> = batch
+            .iter()
+            .map(|wallet| {
+                if deny {
+                    instruction::add_to_denylist(
+                        &context.program_id,
+                        &context.faucet,
+                        &admin,
+                        wallet,
+                    )
+                } else {
+                    instruction::remove_from_denylist(
+                        &context.program_id,
+                        &context.faucet,
+                        &admin,
+                        wallet,
+                    )
+                }
+            })
+            .collect();
+
+        // The batches before a failed one stay applied, their signatures go with the error
+        let signature = context.send(&instructions).map_err(|err| {
+            if signatures.is_empty() {
+                return err;
+            }
+            format!("{} (batches already sent: {})", err, signatures.join(", ")).into()
+        })?;
+        signatures.push(signature.to_string());
+    }
+
+    Ok(json!({
+        "action": if deny { "deny" } else { "undeny" },
+        "wallets": wallets.len(),
+        "signatures": signatures,
+    }))
+}
+
+fn faucet_json(faucet: &FaucetView) -> Value {
+    let faucet_state = &faucet.state;
+    let merkle_root = faucet_state
+        .merkle_root
+        .map(|root| Hash::new_from_array(root).to_string());
+    json!({
+        "address": faucet.address.to_string(),
+        "name": faucet_state.name,
+        "admin": faucet_state.admin.to_string(),
+        "pending_admin": faucet_state.pending_admin.map(|admin| admin.to_string()),
+        "mode": format!("{:?}", faucet_state.mode),
+        "mint": (faucet_state.mode != FaucetMode::Native).then(|| faucet_state.mint.to_string()),
+        "paused": faucet_state.paused,
+        "distribution_amount": faucet_state.distribution_amount,
+        "cooldown_slots": faucet_state.cooldown_slots,
+        "allow_program_owned_recipients": faucet_state.allow_program_owned_recipients,
+        "window_slots": faucet_state.window_slots,
+        "window_cap": faucet_state.window_cap,
+        "recipient_claim_limit": faucet_state.recipient_claim_limit,
+        "recipient_amount_limit": faucet_state.recipient_amount_limit,
+        "allowlist_enabled": faucet_state.allowlist_enabled,
+        "merkle_root": merkle_root,
+        "denylist_len": faucet_state.denylist_len,
+        "attester": faucet_state.attester.map(|attester| attester.to_string()),
+        "balance_lamports": faucet.lamports,
+        "balance_sol": lamports_to_sol(faucet.lamports),
+        "rent_exempt_lamports": faucet.rent_exempt_lamports,
+        "vault_balance": faucet.vault_balance,
+        "available": faucet.available(),
+        "claims_remaining": faucet.claims_remaining(),
+    })
+}
@@ -0,0 +1,508 @@
+use clap::ArgMatches;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_CLUSTER: &str = "devnet";
+pub const DEFAULT_PROGRAM_ID: &str = "5gpW17UnnPPzgdhdoHBBJM75fmZaCvX14DjvtxqXsqCY";
+pub const DEFAULT_KEYPAIR: &str = "admin_keypair.json";
+pub const DEFAULT_FAUCET_NAME: &str = "devnet";
+// Looked up in the working directory unless `--config` or `FAUCET_CONFIG` says otherwise
+pub const DEFAULT_PROJECT_CONFIG: &str = "faucet.toml";
+
+const BUILTIN_CLUSTERS: [(&str, &str); 4] = [
+    ("localnet", "http://127.0.0.1:8899"),
+    ("devnet", "https://api.devnet.solana.com"),
+    ("testnet", "https://api.testnet.solana.com"),
+    ("mainnet-beta", "https://api.mainnet-beta.solana.com"),
+];
+
+/// Where a setting came from, highest priority first
+#[derive(Clone, Debug)]
+pub enum Source {
+    Flag(&'static str),
+    Env(&'static str),
+    ProjectConfig(PathBuf),
+    SolanaConfig(PathBuf),
+    Default,
+}
+
+impl Source {
+    fn describe(&self) -> String {
+        match self {
+            Source::Flag(flag) => format!("--{} flag", flag),
+            Source::Env(var) => format!("{} environment variable", var),
+            Source::ProjectConfig(path) => format!("project config {}", path.display()),
+            Source::SolanaConfig(path) => format!("Solana CLI config {}", path.display()),
+            Source::Default => "default".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Setting {
+    pub value: String,
+    pub source: Source,
+}
+
+/// Settings resolved from flags, then environment variables, then the project config, then
+/// the Solana CLI config
+pub struct Config {
+    pub cluster: Option<Setting>, // profile the URL comes from, `None` for a bare URL
+    pub url: Setting,
+    pub program_id: Setting,
+    pub keypair: Setting,
+    pub faucet_name: Setting,
+}
+
+/// Project config file, every field is optional
+///
+/// ```toml
+/// cluster = "staging"
+/// keypair = "admin_keypair.json"
+///
+/// [clusters.staging]
+/// url = "https://staging-rpc.example.com"
+/// program_id = "..."
+/// ```
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectConfig {
+    cluster: Option<String>,
+    url: Option<String>,
+    program_id: Option<String>,
+    keypair: Option<String>,
+    name: Option<String>,
+    #[serde(default)]
+    clusters: HashMap<String, ClusterProfile>,
+}
+
+/// Named cluster, the built-in localnet, devnet, testnet and mainnet-beta can be overridden
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClusterProfile {
+    url: String,
+    program_id: Option<String>, // where the faucet program is deployed on this cluster
+}
+
+// The URL of the highest layer setting either a URL or a cluster
+enum Endpoint {
+    Url(Setting),
+    Cluster(Setting),
+}
+
+// Everything settings are resolved from, `Config::load` reads them from the process
+struct Layers {
+    flags: HashMap<&'static str, String>, // by argument id
+    env: HashMap<&'static str, String>,   // set and non-empty variables only
+    project: ProjectConfig,
+    project_source: Source,
+    solana: HashMap<String, String>,
+    solana_source: Source,
+}
+
+const FLAG_IDS: [&str; 6] = ["config", "url", "cluster", "program_id", "keypair", "name"];
+const ENV_VARS: [&str; 6] = [
+    "FAUCET_CONFIG",
+    "FAUCET_RPC_URL",
+    "FAUCET_CLUSTER",
+    "FAUCET_PROGRAM_ID",
+    "FAUCET_KEYPAIR",
+    "FAUCET_NAME",
+];
+
+impl Config {
+    pub fn load(matches: &ArgMatches) -> Result<Self, String> {
+        let flags: HashMap<_, _> = FLAG_IDS
+            .into_iter()
+            .filter_map(|id| {
+                matches
+                    .get_one::<String>(id)
+                    .map(|value| (id, value.clone()))
+            })
+            .collect();
+        let env: HashMap<_, _> = ENV_VARS
+            .into_iter()
+            .filter_map(|var| {
+                env::var(var)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (var, value))
+            })
+            .collect();
+
+        let project_path = flags
+            .get("config")
+            .or_else(|| env.get("FAUCET_CONFIG"))
+            .map(PathBuf::from);
+        let (project, project_source) = load_project_config(project_path)?;
+        let (solana, solana_source) = load_solana_config();
+
+        Self::resolve(&Layers {
+            flags,
+            env,
+            project,
+            project_source,
+            solana,
+            solana_source,
+        })
+    }
+
+    fn resolve(layers: &Layers) -> Result<Self, String> {
+        let flag = |id: &str, flag: &'static str| {
+            layers.flags.get(id).map(|value| Setting {
+                value: value.clone(),
+                source: Source::Flag(flag),
+            })
+        };
+        let env_var = |var: &'static str| {
+            layers.env.get(var).map(|value| Setting {
+                value: value.clone(),
+                source: Source::Env(var),
+            })
+        };
+        let project = &layers.project;
+        let project_setting = |value: &Option<String>| {
+            value.clone().map(|value| Setting {
+                value,
+                source: layers.project_source.clone(),
+            })
+        };
+        let solana_setting = |key: &str| {
+            layers.solana.get(key).map(|value| Setting {
+                value: expand_home(value),
+                source: layers.solana_source.clone(),
+            })
+        };
+
+        // Within one layer a URL beats a cluster
+        let endpoint = [
+            flag("url", "url")
+                .map(Endpoint::Url)
+                .or_else(|| flag("cluster", "cluster").map(Endpoint::Cluster)),
+            env_var("FAUCET_RPC_URL")
+                .map(Endpoint::Url)
+                .or_else(|| env_var("FAUCET_CLUSTER").map(Endpoint::Cluster)),
+            project_setting(&project.url)
+                .map(Endpoint::Url)
+                .or_else(|| project_setting(&project.cluster).map(Endpoint::Cluster)),
+            solana_setting("json_rpc_url").map(Endpoint::Url),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+        .unwrap_or(Endpoint::Cluster(Setting {
+            value: DEFAULT_CLUSTER.to_string(),
+            source: Source::Default,
+        }));
+
+        let (cluster, url, profile_program_id) = match endpoint {
+            Endpoint::Url(url) => (None, url, None),
+            Endpoint::Cluster(cluster) => {
+                let profile = cluster_profile(project, &cluster.value)?;
+                let url = Setting {
+                    value: profile.url,
+                    source: cluster.source.clone(),
+                };
+                (Some(cluster), url, project_setting(&profile.program_id))
+            }
+        };
+
+        let program_id = flag("program_id", "program-id")
+            .or_else(|| env_var("FAUCET_PROGRAM_ID"))
+            .or(profile_program_id)
+            .or_else(|| project_setting(&project.program_id))
+            .unwrap_or_else(|| default_setting(DEFAULT_PROGRAM_ID));
+        let keypair = flag("keypair", "keypair")
+            .or_else(|| env_var("FAUCET_KEYPAIR"))
+            .or_else(|| project_setting(&project.keypair))
+            .or_else(|| solana_setting("keypair_path"))
+            .unwrap_or_else(|| default_setting(DEFAULT_KEYPAIR));
+        let faucet_name = flag("name", "name")
+            .or_else(|| env_var("FAUCET_NAME"))
+            .or_else(|| project_setting(&project.name))
+            .unwrap_or_else(|| default_setting(DEFAULT_FAUCET_NAME));
+
+        Ok(Self {
+            cluster,
+            url,
+            program_id,
+            keypair,
+            faucet_name,
+        })
+    }
+
+    /// Every setting with where it came from
+    pub fn to_json(&self) -> Value {
+        let setting = |setting: &Setting| {
+            json!({
+                "value": setting.value,
+                "source": setting.source.describe(),
+            })
+        };
+
+        json!({
+            "cluster": self.cluster.as_ref().map(setting),
+            "url": setting(&self.url),
+            "program_id": setting(&self.program_id),
+            "keypair": setting(&self.keypair),
+            "name": setting(&self.faucet_name),
+        })
+    }
+}
+
+fn default_setting(value: &str) -> Setting {
+    Setting {
+        value: value.to_string(),
+        source: Source::Default,
+    }
+}
+
+// Profiles of the project config come first, so they can override the built-in clusters
+fn cluster_profile(project: &ProjectConfig, name: &str) -> Result<ClusterProfile, String> {
+    if let Some(profile) = project.clusters.get(name) {
+        return Ok(profile.clone());
+    }
+
+    BUILTIN_CLUSTERS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, url)| ClusterProfile {
+            url: url.to_string(),
+            program_id: None,
+        })
+        .ok_or_else(|| {
+            format!(
+                "Unknown cluster {:?}, expected localnet, devnet, testnet, mainnet-beta \
+                 or a [clusters.{}] profile in the project config",
+                name, name
+            )
+        })
+}
+
+// An explicit path has to exist, the default one is optional
+fn load_project_config(path: Option<PathBuf>) -> Result<(ProjectConfig, Source), String> {
+    let (path, required) = match path {
+        Some(path) => (path, true),
+        None => (PathBuf::from(DEFAULT_PROJECT_CONFIG), false),
+    };
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) if !required => return Ok((ProjectConfig::default(), Source::Default)),
+        Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+    };
+    let project = toml::from_str(&text)
+        .map_err(|err| format!("Failed to parse {}: {}", path.display(), err))?;
+
+    Ok((project, Source::ProjectConfig(path)))
+}
+
+// The Solana CLI config is flat YAML, `key: value` per line is all there is to read
+fn load_solana_config() -> (HashMap<String, String>, Source) {
+    let Some(path) = home_dir().map(|home| home.join(".config/solana/cli/config.yml")) else {
+        return (HashMap::new(), Source::Default);
+    };
+    let Ok(text) = fs::read_to_string(&path) else {
+        return (HashMap::new(), Source::Default);
+    };
+
+    (parse_solana_config(&text), Source::SolanaConfig(path))
+}
+
+fn parse_solana_config(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| {
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            (key.trim().to_string(), value.to_string())
+        })
+        .filter(|(key, value)| !key.starts_with('#') && !value.is_empty())
+        .collect()
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest).display().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT_PATH: &str = "faucet.toml";
+    const SOLANA_PATH: &str = "/home/dev/.config/solana/cli/config.yml";
+
+    fn layers(
+        flags: &[(&'static str, &str)],
+        env: &[(&'static str, &str)],
+        project: &str,
+        solana: &str,
+    ) -> Layers {
+        Layers {
+            flags: flags
+                .iter()
+                .map(|(id, value)| (*id, value.to_string()))
+                .collect(),
+            env: env
+                .iter()
+                .map(|(var, value)| (*var, value.to_string()))
+                .collect(),
+            project: toml::from_str(project).unwrap(),
+            project_source: Source::ProjectConfig(PathBuf::from(PROJECT_PATH)),
+            solana: parse_solana_config(solana),
+            solana_source: Source::SolanaConfig(PathBuf::from(SOLANA_PATH)),
+        }
+    }
+
+    fn resolve(layers: &Layers) -> Config {
+        Config::resolve(layers).unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = resolve(&layers(&[], &[], "", ""));
+
+        assert_eq!(config.cluster.unwrap().value, DEFAULT_CLUSTER);
+        assert_eq!(config.url.value, "https://api.devnet.solana.com");
+        assert!(matches!(config.url.source, Source::Default));
+        assert_eq!(config.program_id.value, DEFAULT_PROGRAM_ID);
+        assert_eq!(config.keypair.value, DEFAULT_KEYPAIR);
+        assert_eq!(config.faucet_name.value, DEFAULT_FAUCET_NAME);
+    }
+
+    #[test]
+    fn test_flag_beats_env_beats_project_beats_solana_config() {
+        let flag = ("keypair", "flag.json");
+        let env = ("FAUCET_KEYPAIR", "env.json");
+        let project = r#"keypair = "project.json""#;
+        let solana = "keypair_path: /keys/solana.json";
+
+        let config = resolve(&layers(&[flag], &[env], project, solana));
+        assert_eq!(config.keypair.value, "flag.json");
+        assert!(matches!(config.keypair.source, Source::Flag("keypair")));
+
+        let config = resolve(&layers(&[], &[env], project, solana));
+        assert_eq!(config.keypair.value, "env.json");
+        assert!(matches!(
+            config.keypair.source,
+            Source::Env("FAUCET_KEYPAIR")
+        ));
+
+        let config = resolve(&layers(&[], &[], project, solana));
+        assert_eq!(config.keypair.value, "project.json");
+        assert!(matches!(config.keypair.source, Source::ProjectConfig(_)));
+
+        let config = resolve(&layers(&[], &[], "", solana));
+        assert_eq!(config.keypair.value, "/keys/solana.json");
+        assert!(matches!(config.keypair.source, Source::SolanaConfig(_)));
+    }
+
+    #[test]
+    fn test_url_beats_cluster_within_a_layer() {
+        let config = resolve(&layers(
+            &[],
+            &[
+                ("FAUCET_RPC_URL", "http://rpc.example.com"),
+                ("FAUCET_CLUSTER", "testnet"),
+            ],
+            "",
+            "",
+        ));
+        assert!(config.cluster.is_none());
+        assert_eq!(config.url.value, "http://rpc.example.com");
+
+        // Across layers the higher one wins, whether it sets a URL or a cluster
+        let config = resolve(&layers(
+            &[("cluster", "localnet")],
+            &[("FAUCET_RPC_URL", "http://rpc.example.com")],
+            "",
+            "json_rpc_url: https://api.mainnet-beta.solana.com",
+        ));
+        assert_eq!(config.cluster.unwrap().value, "localnet");
+        assert_eq!(config.url.value, "http://127.0.0.1:8899");
+        assert!(matches!(config.url.source, Source::Flag("cluster")));
+
+        let config = resolve(&layers(
+            &[],
+            &[],
+            "",
+            "json_rpc_url: https://api.mainnet-beta.solana.com",
+        ));
+        assert_eq!(config.url.value, "https://api.mainnet-beta.solana.com");
+    }
+
+    #[test]
+    fn test_profile_program_id_beats_global_program_id() {
+        let project = r#"
+            cluster = "staging"
+            program_id = "GlobalProgram"
+
+            [clusters.staging]
+            url = "https://staging-rpc.example.com"
+            program_id = "StagingProgram"
+
+            [clusters.devnet]
+            url = "https://devnet-rpc.example.com"
+        "#;
+
+        let config = resolve(&layers(&[], &[], project, ""));
+        assert_eq!(config.url.value, "https://staging-rpc.example.com");
+        assert_eq!(config.program_id.value, "StagingProgram");
+
+        // Flags and the environment still beat the profile
+        let config = resolve(&layers(
+            &[],
+            &[("FAUCET_PROGRAM_ID", "EnvProgram")],
+            project,
+            "",
+        ));
+        assert_eq!(config.program_id.value, "EnvProgram");
+
+        // Profiles override the built-in clusters, the global program ID fills in
+        let config = resolve(&layers(&[("cluster", "devnet")], &[], project, ""));
+        assert_eq!(config.url.value, "https://devnet-rpc.example.com");
+        assert_eq!(config.program_id.value, "GlobalProgram");
+    }
+
+    #[test]
+    fn test_unknown_cluster() {
+        let result = Config::resolve(&layers(&[("cluster", "staging")], &[], "", ""));
+        assert!(matches!(result, Err(err) if err.contains("Unknown cluster \"staging\"")));
+    }
+
+    #[test]
+    fn test_parse_solana_config() {
+        let settings = parse_solana_config(
+            "---\n\
+             json_rpc_url: \"https://api.devnet.solana.com\"\n\
+             websocket_url: ''\n\
+             keypair_path: /home/dev/.config/solana/id.json\n\
+             # commitment: finalized\n\
+             commitment: confirmed\n",
+        );
+
+        assert_eq!(
+            settings.get("json_rpc_url").unwrap(),
+            "https://api.devnet.solana.com"
+        );
+        assert_eq!(
+            settings.get("keypair_path").unwrap(),
+            "/home/dev/.config/solana/id.json"
+        );
+        assert_eq!(settings.get("commitment").unwrap(), "confirmed");
+        assert!(!settings.contains_key("websocket_url"));
+        assert!(!settings.contains_key("# commitment"));
+        assert_eq!(settings.len(), 3);
+    }
+}
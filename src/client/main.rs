@@ -1,207 +1,491 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use clap::{crate_version, value_parser, Arg, ArgAction, ArgMatches, Command};
+use serde_json::Value;
+use simple_token_faucet::instruction::ConfigUpdate;
+use simple_token_faucet::rpc::FaucetClient;
+use simple_token_faucet::state::{try_find_faucet_address, FaucetMode, MAX_FAUCET_NAME_LEN};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
-    instruction::{AccountMeta, Instruction},
-    native_token::lamports_to_sol,
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    signer::keypair::{read_keypair_file, write_keypair_file},
-    sysvar,
-    transaction::Transaction,
+    signature::{Keypair, Signature},
+    signer::{keypair::read_keypair_file, Signer},
 };
-use std::str::FromStr;
+use std::{error::Error, process::exit, str::FromStr};
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-enum FaucetInstruction {
-    Initialize { distribution_amount: u64 },
-    RequestTokens,
-    ReplenishTokens { amount: u64 },
+use config::Config;
+
+mod commands;
+mod config;
+
+/// Commands return their result as JSON, `--output` decides how it's printed
+pub type CliResult = Result<Value, Box<dyn Error>>;
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Display,
+    Json,
 }
 
-fn main() {
-    let rpc_url = "https://api.devnet.solana.com".to_string();
-    let client = RpcClient::new(rpc_url);
-
-    let raw_program_id = "5gpW17UnnPPzgdhdoHBBJM75fmZaCvX14DjvtxqXsqCY";
-    let program_id = Pubkey::from_str(raw_program_id).expect("Failed to parse program ID");
-
-    let faucet_keypair =
-        read_keypair_file("faucet_keypair.json").expect("Failed to read faucet keypair");
-
-    // Check faucet balance before processing
-    check_faucet_balance(&client, &faucet_keypair.pubkey(), 1).expect("Faucet balance is too low");
-
-    // Initialize faucet (comment out once initialized)
-    initialize_faucet(
-        &client,
-        &program_id,
-        &faucet_keypair,
-        &faucet_keypair,
-        100_000_000,
-    );
-
-    // Request tokens from the faucet
-    request_tokens(&client, &program_id, &faucet_keypair);
-
-    // Replenish token
-    replenish_tokens(
-        &client,
-        &program_id,
-        &faucet_keypair,
-        &faucet_keypair,
-        100_000_000,
-    );
+/// What every command needs: the cluster, the program, the signer and the faucet
+pub struct Context {
+    pub client: FaucetClient,
+    pub program_id: Pubkey,
+    pub keypair: Keypair,
+    pub faucet: Pubkey,
+    pub faucet_name: String,
 }
 
-fn initialize_faucet(
-    client: &RpcClient,
-    program_id: &Pubkey,
-    faucet_keypair: &Keypair,
-    admin_keypair: &Keypair,
-    distribution_amount: u64,
-) {
-    let instruction = Instruction::new_with_borsh(
-        *program_id,
-        &FaucetInstruction::Initialize {
-            distribution_amount,
+impl Context {
+    // The context keypair signs and pays the fees
+    pub fn send(&self, instructions: &[Instruction]) -> Result<Signature, Box<dyn Error>> {
+        Ok(self.client.send(instructions, &self.keypair)?)
+    }
+}
+
+fn main() {
+    let matches = app().get_matches();
+    let output = match matches.get_one::<String>("output").map(String::as_str) {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Display,
+    };
+
+    match run(&matches) {
+        Ok(value) => match output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
+            OutputFormat::Display => print_display(&value, 0),
         },
-        vec![
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(admin_keypair.pubkey(), true),
-            AccountMeta::new_readonly(sysvar::rent::id(), true),
-        ],
-    );
-
-    let recent_blockhash = client.get_latest_blockhash().unwrap();
-    let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&admin_keypair.pubkey()),
-        &[admin_keypair],
-        recent_blockhash,
-    );
-
-    let signature = client.send_and_confirm_transaction(&transaction).unwrap();
-    println!("Faucet initialized. Transaction signature: {}", signature);
+        Err(err) => {
+            match output {
+                OutputFormat::Json => {
+                    println!("{}", serde_json::json!({ "error": err.to_string() }))
+                }
+                OutputFormat::Display => eprintln!("Error: {}", err),
+            }
+            exit(1);
+        }
+    }
 }
 
-fn request_tokens(client: &RpcClient, program_id: &Pubkey, faucet_keypair: &Keypair) {
-    let user_keypair = generate_and_save_keypair();
-    println!("User keypair pubkey: {}", user_keypair.pubkey());
-
-    let instruction = Instruction::new_with_borsh(
-        *program_id,
-        &FaucetInstruction::RequestTokens,
-        vec![
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(user_keypair.pubkey(), false),
-        ],
-    );
-
-    let recent_blockhash = client.get_latest_blockhash().unwrap();
-    let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&user_keypair.pubkey()),
-        &[&user_keypair],
-        recent_blockhash,
-    );
-
-    let signature = client.send_and_confirm_transaction(&transaction).unwrap();
-    println!("Transaction signature: {}", signature);
-
-    let faucet_balance = client.get_balance(&faucet_keypair.pubkey()).unwrap();
-    println!(
-        "Faucet current balance: {} lamports ({} SOL)",
-        faucet_balance,
-        lamports_to_sol(faucet_balance)
-    );
-
-    let user_balance = client.get_balance(&user_keypair.pubkey()).unwrap();
-    println!(
-        "User current balance: {} lamports ({} SOL)",
-        user_balance,
-        lamports_to_sol(user_balance)
-    );
-
-    let transaction_url = format!(
-        "https://explorer.solana.com/tx/{}?cluster=devnet",
-        signature
-    );
-    println!("Transaction URL: {}", transaction_url);
+fn app() -> Command<'static> {
+    let amount = Arg::new("amount")
+        .long("amount")
+        .value_name("AMOUNT")
+        .value_parser(value_parser!(u64))
+        .takes_value(true);
+    let destination = Arg::new("destination")
+        .long("destination")
+        .value_name("ADDRESS")
+        .takes_value(true)
+        .help("Receives the lamports [default: the keypair's address]");
+
+    Command::new("client")
+        .version(crate_version!())
+        .about("Command-line client of the simple token faucet program")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .short('u')
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .help("JSON RPC URL [env: FAUCET_RPC_URL]"),
+        )
+        .arg(
+            Arg::new("cluster")
+                .long("cluster")
+                .short('c')
+                .value_name("NAME")
+                .takes_value(true)
+                .global(true)
+                .conflicts_with("url")
+                .help(
+                    "Cluster profile: localnet, devnet, testnet, mainnet-beta or one of the \
+                     project config [env: FAUCET_CLUSTER] [default: devnet]",
+                ),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Project config file [env: FAUCET_CONFIG] [default: faucet.toml]"),
+        )
+        .arg(
+            Arg::new("program_id")
+                .long("program-id")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .global(true)
+                .help("Faucet program id [env: FAUCET_PROGRAM_ID]"),
+        )
+        .arg(
+            Arg::new("keypair")
+                .long("keypair")
+                .short('k')
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help(
+                    "Keypair signing and paying for transactions, the admin for admin commands \
+                     [env: FAUCET_KEYPAIR] [default: admin_keypair.json]",
+                ),
+        )
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .value_name("NAME")
+                .takes_value(true)
+                .global(true)
+                .help("Faucet name [env: FAUCET_NAME] [default: devnet]"),
+        )
+        .arg(
+            Arg::new("creator")
+                .long("creator")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .global(true)
+                .help("Creator of the faucet [default: the keypair's address]"),
+        )
+        .arg(
+            Arg::new("faucet")
+                .long("faucet")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .global(true)
+                .conflicts_with("creator")
+                .help("Faucet address, instead of deriving it from the creator and name"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("FORMAT")
+                .takes_value(true)
+                .global(true)
+                .value_parser(["display", "json"])
+                .default_value("display")
+                .help("Output format"),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Create the faucet, the keypair becomes its admin")
+                .arg(
+                    amount
+                        .clone()
+                        .required(true)
+                        .help("Lamports or base units paid out per claim"),
+                )
+                .arg(
+                    u64_arg(
+                        "cooldown_slots",
+                        "cooldown-slots",
+                        "Slots a recipient waits between two claims",
+                    )
+                    .default_value("150"),
+                )
+                .arg(
+                    Arg::new("allow_program_owned_recipients")
+                        .long("allow-program-owned-recipients")
+                        .action(ArgAction::SetTrue)
+                        .help("Let accounts owned by programs claim"),
+                )
+                .arg(
+                    Arg::new("mode")
+                        .long("mode")
+                        .value_name("MODE")
+                        .takes_value(true)
+                        .value_parser(["native", "token-vault", "mint-authority"])
+                        .default_value("native")
+                        .requires_if("token-vault", "mint")
+                        .requires_if("mint-authority", "mint")
+                        .help("What the faucet pays out"),
+                )
+                .arg(
+                    Arg::new("mint")
+                        .long("mint")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .help("Mint of token faucets"),
+                ),
+        )
+        .subcommand(
+            Command::new("request")
+                .about("Claim from the faucet, the keypair pays the fees")
+                .arg(
+                    Arg::new("recipient")
+                        .long("recipient")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .help("Wallet receiving the payout [default: the keypair's address]"),
+                ),
+        )
+        .subcommand(
+            Command::new("replenish")
+                .about("Move funds from the admin into the faucet")
+                .arg(
+                    amount
+                        .clone()
+                        .required(true)
+                        .help("Lamports or base units to deposit"),
+                ),
+        )
+        .subcommand(Command::new("show").about("Show the faucet configuration and balance"))
+        .subcommand(Command::new("list").about("List every faucet of the program"))
+        .subcommand(
+            Command::new("config")
+                .about("Show the effective configuration and where it comes from"),
+        )
+        .subcommand(
+            Command::new("update-config")
+                .about("Change the faucet configuration, unset flags are kept")
+                .arg(
+                    amount
+                        .clone()
+                        .help("Lamports or base units paid out per claim"),
+                )
+                .arg(u64_arg(
+                    "cooldown_slots",
+                    "cooldown-slots",
+                    "Slots a recipient waits between two claims",
+                ))
+                .arg(bool_arg(
+                    "allow_program_owned_recipients",
+                    "allow-program-owned-recipients",
+                    "Let accounts owned by programs claim",
+                ))
+                .arg(u64_arg(
+                    "window_slots",
+                    "window-slots",
+                    "Length of the distribution window, 0 disables the cap",
+                ))
+                .arg(u64_arg(
+                    "window_cap",
+                    "window-cap",
+                    "Most the faucet pays out within one window",
+                ))
+                .arg(u64_arg(
+                    "recipient_claim_limit",
+                    "recipient-claim-limit",
+                    "Lifetime claims of one recipient, 0 for no limit",
+                ))
+                .arg(u64_arg(
+                    "recipient_amount_limit",
+                    "recipient-amount-limit",
+                    "Lifetime amount of one recipient, 0 for no limit",
+                ))
+                .arg(bool_arg(
+                    "allowlist",
+                    "allowlist",
+                    "Only let allowlisted wallets claim",
+                )),
+        )
+        .subcommand(
+            Command::new("withdraw")
+                .about("Move lamports out of the faucet, keeping it rent-exempt")
+                .arg(amount.required(true).help("Lamports to withdraw"))
+                .arg(destination.clone()),
+        )
+        .subcommand(
+            Command::new("close")
+                .about("Close the faucet and reclaim all of its lamports")
+                .arg(destination),
+        )
+        .subcommand(
+            Command::new("deny")
+                .about("Add wallets to the denylist")
+                .arg(wallets_arg()),
+        )
+        .subcommand(
+            Command::new("undeny")
+                .about("Remove wallets from the denylist")
+                .arg(wallets_arg()),
+        )
 }
 
-fn replenish_tokens(
-    client: &RpcClient,
-    program_id: &Pubkey,
-    faucet_keypair: &Keypair,
-    admin_keypair: &Keypair,
-    amount: u64,
-) {
-    let instruction = Instruction::new_with_borsh(
-        *program_id,
-        &FaucetInstruction::ReplenishTokens { amount },
-        vec![
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(admin_keypair.pubkey(), true),
-        ],
-    );
-
-    let blockhash = client.get_latest_blockhash().unwrap();
-    let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&admin_keypair.pubkey()),
-        &[admin_keypair],
-        blockhash,
-    );
-
-    let signature = client.send_and_confirm_transaction(&transaction).unwrap();
-    println!("Transaction replenished. Signature: {}", signature);
-
-    let balance = client.get_balance(&faucet_keypair.pubkey()).unwrap();
-    println!(
-        "Faucet balance: {} lamports ({} SOL)",
-        balance,
-        lamports_to_sol(balance)
-    );
+fn u64_arg(id: &'static str, long: &'static str, help: &'static str) -> Arg<'static> {
+    Arg::new(id)
+        .long(long)
+        .value_name("NUMBER")
+        .value_parser(value_parser!(u64))
+        .takes_value(true)
+        .help(help)
 }
 
-fn generate_and_save_keypair() -> Keypair {
-    let file_path = "user_keypair.json";
+fn bool_arg(id: &'static str, long: &'static str, help: &'static str) -> Arg<'static> {
+    Arg::new(id)
+        .long(long)
+        .value_name("BOOL")
+        .value_parser(value_parser!(bool))
+        .takes_value(true)
+        .help(help)
+}
 
-    if !std::path::Path::new(file_path).exists() {
-        println!("Keypair file does not exist, creating one!");
+fn wallets_arg() -> Arg<'static> {
+    Arg::new("wallets")
+        .value_name("ADDRESS")
+        .takes_value(true)
+        .multiple_values(true)
+        .required(true)
+}
+
+fn run(matches: &ArgMatches) -> CliResult {
+    let config = Config::load(matches)?;
+    if let Some(("config", _)) = matches.subcommand() {
+        return Ok(config.to_json());
+    }
 
-        let user_keypair = Keypair::new();
-        write_keypair_file(&user_keypair, file_path).expect("Failed to write keypair to file");
+    let context = context(matches, &config)?;
+    let signer = context.keypair.pubkey();
 
-        println!("Keypair saved to user_keypair.json!");
-        user_keypair
-    } else {
-        println!("Keypair file already exists");
-        read_keypair_file(file_path).expect("Failed to read keypair file")
+    match matches.subcommand() {
+        Some(("init", sub)) => commands::init(
+            &context,
+            *sub.get_one::<u64>("amount").unwrap(),
+            *sub.get_one::<u64>("cooldown_slots").unwrap(),
+            sub.get_flag("allow_program_owned_recipients"),
+            match sub.get_one::<String>("mode").unwrap().as_str() {
+                "token-vault" => FaucetMode::TokenVault,
+                "mint-authority" => FaucetMode::MintAuthority,
+                _ => FaucetMode::Native,
+            },
+            optional_pubkey(sub, "mint")?,
+        ),
+        Some(("request", sub)) => commands::request(
+            &context,
+            optional_pubkey(sub, "recipient")?.unwrap_or(signer),
+        ),
+        Some(("replenish", sub)) => {
+            commands::replenish(&context, *sub.get_one::<u64>("amount").unwrap())
+        }
+        Some(("show", _)) => commands::show(&context),
+        Some(("list", _)) => commands::list(&context),
+        Some(("update-config", sub)) => commands::update_config(
+            &context,
+            ConfigUpdate {
+                distribution_amount: sub.get_one::<u64>("amount").copied(),
+                cooldown_slots: sub.get_one::<u64>("cooldown_slots").copied(),
+                allow_program_owned_recipients: sub
+                    .get_one::<bool>("allow_program_owned_recipients")
+                    .copied(),
+                window_slots: sub.get_one::<u64>("window_slots").copied(),
+                window_cap: sub.get_one::<u64>("window_cap").copied(),
+                recipient_claim_limit: sub.get_one::<u64>("recipient_claim_limit").copied(),
+                recipient_amount_limit: sub.get_one::<u64>("recipient_amount_limit").copied(),
+                allowlist_enabled: sub.get_one::<bool>("allowlist").copied(),
+            },
+        ),
+        Some(("withdraw", sub)) => commands::withdraw(
+            &context,
+            *sub.get_one::<u64>("amount").unwrap(),
+            optional_pubkey(sub, "destination")?.unwrap_or(signer),
+        ),
+        Some(("close", sub)) => commands::close(
+            &context,
+            optional_pubkey(sub, "destination")?.unwrap_or(signer),
+        ),
+        Some((command @ ("deny" | "undeny"), sub)) => {
+            let wallets = sub
+                .get_many::<String>("wallets")
+                .unwrap()
+                .map(|wallet| parse_pubkey(wallet))
+                .collect::<Result<Vec<_>, _>>()?;
+            commands::update_denylist(&context, &wallets, command == "deny")
+        }
+        _ => unreachable!("clap requires a subcommand"),
     }
 }
 
-// Check the SOL balance in the faucet account
-fn check_faucet_balance(
-    client: &RpcClient,
-    faucet_pubkey: &Pubkey,
-    min_balance: u32,
-) -> Result<(), String> {
-    let faucet_balance = client.get_balance(&faucet_pubkey).unwrap_or(0);
-    let balance_in_sol = lamports_to_sol(faucet_balance);
-
-    if balance_in_sol < min_balance as f64 {
-        return Err(format!(
-            "Faucet balance is too low. Current balance: {} SOL, Minimum required: {} SOL",
-            balance_in_sol, min_balance,
-        ));
+fn context(matches: &ArgMatches, config: &Config) -> Result<Context, Box<dyn Error>> {
+    let program_id = parse_pubkey(&config.program_id.value)?;
+    let keypair_path = &config.keypair.value;
+    let keypair = read_keypair_file(keypair_path)
+        .map_err(|err| format!("Failed to read keypair {}: {}", keypair_path, err))?;
+    let faucet_name = config.faucet_name.value.clone();
+
+    // Every faucet is a PDA of its creator and name, no keypair needed
+    let faucet = match optional_pubkey(matches, "faucet")? {
+        Some(faucet) => faucet,
+        None => {
+            let creator = optional_pubkey(matches, "creator")?.unwrap_or(keypair.pubkey());
+            try_find_faucet_address(&program_id, &creator, &faucet_name)
+                .ok_or_else(|| {
+                    format!(
+                        "Faucet name {:?} is longer than {} bytes",
+                        faucet_name, MAX_FAUCET_NAME_LEN
+                    )
+                })?
+                .0
+        }
+    };
+
+    Ok(Context {
+        client: FaucetClient::new(
+            RpcClient::new_with_commitment(config.url.value.clone(), CommitmentConfig::confirmed()),
+            program_id,
+        ),
+        program_id,
+        keypair,
+        faucet,
+        faucet_name,
+    })
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey, Box<dyn Error>> {
+    Pubkey::from_str(value).map_err(|_| format!("Invalid address {:?}", value).into())
+}
+
+fn optional_pubkey(matches: &ArgMatches, id: &str) -> Result<Option<Pubkey>, Box<dyn Error>> {
+    matches
+        .get_one::<String>(id)
+        .map(|value| parse_pubkey(value))
+        .transpose()
+}
+
+// Prints command results as indented `key: value` lines
+fn print_display(value: &Value, indent: usize) {
+    let padding = " ".repeat(indent);
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                match field {
+                    Value::Object(_) | Value::Array(_) => {
+                        println!("{}{}:", padding, key);
+                        print_display(field, indent + 2);
+                    }
+                    _ => println!("{}{}: {}", padding, key, display_scalar(field)),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                print_display(item, indent);
+                if item.is_object() {
+                    println!();
+                }
+            }
+        }
+        _ => println!("{}{}", padding, display_scalar(value)),
     }
+}
 
-    println!(
-        "Faucet current balance: {} lamports ({} SOL)",
-        faucet_balance,
-        lamports_to_sol(faucet_balance)
-    );
+fn display_scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "none".to_string(),
+        _ => value.to_string(),
+    }
+}
 
-    Ok(())
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_app() {
+        app().debug_assert();
+    }
+}
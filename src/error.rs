@@ -0,0 +1,146 @@
+use num_derive::FromPrimitive;
+use solana_program::{decode_error::DecodeError, program_error::ProgramError};
+use thiserror::Error;
+
+/// Errors returned by the faucet program as `ProgramError::Custom(code)`.
+///
+/// The codes are part of the program's interface, never reorder or reuse them.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum FaucetError {
+    /// The recipient already claimed within the faucet's cooldown window
+    #[error("Recipient is still in its cooldown window")]
+    CooldownActive = 0,
+    /// Instruction data could not be decoded
+    #[error("Invalid instruction data")]
+    InvalidInstruction = 1,
+    /// An account is not owned by the faucet program
+    #[error("Account is not owned by the faucet program")]
+    IncorrectOwner = 2,
+    /// A required signature is missing
+    #[error("Missing required signature")]
+    MissingSignature = 3,
+    /// The signer is not the faucet admin
+    #[error("Signer is not the faucet admin")]
+    NotAdmin = 4,
+    /// The faucet balance can't cover the requested amount
+    #[error("Insufficient faucet funds")]
+    InsufficientFunds = 5,
+    /// The faucet account data could not be decoded
+    #[error("Invalid faucet account data")]
+    InvalidFaucetData = 6,
+    /// The faucet account has already been initialized
+    #[error("Faucet is already initialized")]
+    AlreadyInitialized = 7,
+    /// Reserved, never returned since Initialize creates the faucet PDA rent-exempt
+    #[error("Faucet account is not rent-exempt")]
+    NotRentExempt = 8,
+    /// The claim record does not belong to this faucet and recipient
+    #[error("Invalid claim record account")]
+    InvalidClaimRecord = 9,
+    /// The wrong number of accounts was passed
+    #[error("Incorrect number of accounts")]
+    IncorrectAccountCount = 10,
+    /// An amount computation overflowed
+    #[error("Amount overflow")]
+    AmountOverflow = 11,
+    /// The faucet account has not been initialized yet
+    #[error("Faucet is not initialized")]
+    UninitializedFaucet = 12,
+    /// The recipient account can't receive faucet payouts
+    #[error("Invalid recipient account")]
+    InvalidRecipient = 13,
+    /// There is no pending admin, or the signer is not the pending admin
+    #[error("Signer is not the pending faucet admin")]
+    NotPendingAdmin = 14,
+    /// The destination of a withdrawal can't receive the lamports
+    #[error("Invalid destination account")]
+    InvalidDestination = 15,
+    /// Payouts are paused by the admin
+    #[error("Faucet is paused")]
+    FaucetPaused = 16,
+    /// The faucet account is not the PDA derived from the admin
+    #[error("Invalid faucet address")]
+    InvalidFaucetAddress = 17,
+    /// The token or associated token program is not supported
+    #[error("Invalid token program")]
+    InvalidTokenProgram = 18,
+    /// The mint is not the faucet mint or can't be decoded
+    #[error("Invalid mint")]
+    InvalidMint = 19,
+    /// A token account is not the expected one or can't be decoded
+    #[error("Invalid token account")]
+    InvalidTokenAccount = 20,
+    /// The mint authority is not the expected one
+    #[error("Invalid mint authority")]
+    InvalidMintAuthority = 21,
+    /// The instruction does not apply to the faucet mode
+    #[error("Instruction not supported in this faucet mode")]
+    UnsupportedFaucetMode = 22,
+    /// The recipient requires a memo and the memo program is missing or wrong
+    #[error("Missing or invalid memo program")]
+    InvalidMemoProgram = 23,
+    /// The faucet name is longer than `MAX_FAUCET_NAME_LEN` bytes
+    #[error("Invalid faucet name")]
+    InvalidFaucetName = 24,
+    /// The faucet paid out its cap for the current window
+    #[error("Distribution window cap reached")]
+    WindowCapReached = 25,
+    /// The recipient used up its lifetime allowance
+    #[error("Recipient claim limit reached")]
+    ClaimLimitReached = 26,
+    /// The recipient has no allowlist entry
+    #[error("Recipient is not allowlisted")]
+    NotAllowlisted = 27,
+    /// The allowlist entry account is not the PDA of the faucet and wallet
+    #[error("Invalid allowlist entry")]
+    InvalidAllowlistEntry = 28,
+    /// The Merkle proof doesn't link the recipient and amount to the root
+    #[error("Invalid Merkle proof")]
+    InvalidMerkleProof = 29,
+    /// The recipient already claimed its Merkle leaf
+    #[error("Merkle leaf already claimed")]
+    AlreadyClaimed = 30,
+    /// The claim receipt account is not the PDA of the faucet, root and wallet
+    #[error("Invalid claim receipt")]
+    InvalidClaimReceipt = 31,
+    /// The recipient is on the faucet's denylist
+    #[error("Recipient is denylisted")]
+    Denylisted = 32,
+    /// The denylist entry account is not the PDA of the faucet and wallet
+    #[error("Invalid denylist entry")]
+    InvalidDenylistEntry = 33,
+    /// The claim isn't preceded by an Ed25519 check of an attestation for it by the attester
+    #[error("Invalid attestation")]
+    InvalidAttestation = 34,
+    /// The attestation is past its expiry slot
+    #[error("Attestation expired")]
+    AttestationExpired = 35,
+    /// The attestation nonce was already used
+    #[error("Attestation nonce already used")]
+    NonceAlreadyUsed = 36,
+    /// The nonce account is not the PDA of the faucet and attestation nonce
+    #[error("Invalid attestation nonce account")]
+    InvalidNonceAccount = 37,
+    /// A distribution window needs a non-zero cap, a zero cap would refuse every claim
+    #[error("Invalid distribution window")]
+    InvalidWindowConfig = 38,
+}
+
+impl From<FaucetError> for ProgramError {
+    fn from(e: FaucetError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for FaucetError {
+    fn type_of() -> &'static str {
+        "FaucetError"
+    }
+}
+
+impl FaucetError {
+    /// Decodes the code of a `ProgramError::Custom` / `InstructionError::Custom`
+    pub fn from_code(code: u32) -> Option<Self> {
+        num_traits::FromPrimitive::from_u32(code)
+    }
+}
@@ -0,0 +1,1092 @@
+//! Instructions of the faucet program, and builders of each with the accounts it expects
+//!
+//! Clients, tests and downstream crates build instructions here, so the account lists
+//! only live next to the program.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use thiserror::Error;
+
+use crate::{
+    error::FaucetError,
+    state::{
+        find_allowlist_entry_address, find_attestation_nonce_address, find_claim_receipt_address,
+        find_claim_record_address, find_denylist_entry_address, FaucetMode, FaucetState,
+    },
+};
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum FaucetInstruction {
+    /// Creates the faucet PDA `name` of the admin and writes its configuration
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet PDA, see `find_faucet_address`
+    /// 1. `[writable, signer]` Admin, pays the faucet rent
+    /// 2. `[]` System program
+    ///
+    /// `FaucetMode::TokenVault` also expects:
+    /// 3. `[]` Mint
+    /// 4. `[writable]` Vault, associated token account of the faucet PDA
+    /// 5. `[]` Token program
+    /// 6. `[]` Associated token account program
+    ///
+    /// `FaucetMode::MintAuthority` also expects:
+    /// 3. `[]` Mint, its mint authority must already be the faucet PDA
+    /// 4. `[]` Token program
+    Initialize {
+        name: String,
+        distribution_amount: u64,
+        cooldown_slots: u64,
+        allow_program_owned_recipients: bool,
+        mode: FaucetMode,
+    }, // instruction variant with struct-like pattern
+    /// Sends `distribution_amount` lamports from the faucet to the recipient
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[writable]` Recipient, system-owned unless the faucet allows otherwise
+    /// 2. `[writable]` Claim record PDA of the recipient, see `find_claim_record_address`
+    /// 3. `[writable, signer]` Payer of the claim record rent, may be the recipient itself
+    /// 4. `[]` System program
+    ///
+    /// Then, only while the matching check is on:
+    /// - `[]` Allowlist entry PDA of the recipient, see `find_allowlist_entry_address`
+    /// - `[writable]` Claim receipt PDA of the recipient under the Merkle root, see
+    ///   `find_claim_receipt_address`, created with the payer's lamports
+    /// - `[]` Denylist entry PDA of the recipient, see `find_denylist_entry_address`, needed
+    ///   while the denylist holds at least one wallet
+    /// - `[writable]` Nonce PDA of the attestation, see `find_attestation_nonce_address`,
+    ///   created with the payer's lamports, and
+    ///   `[]` Instructions sysvar, both needed while an attester is set
+    ///
+    /// While a Merkle root is set, `merkle_claim` proves the recipient's leaf and the leaf
+    /// amount is paid instead of `distribution_amount`.
+    ///
+    /// While an attester is set, the instruction right before the claim must be an Ed25519
+    /// program check of the attester's signature over the `attestation::Attestation` of the
+    /// recipient and the paid amount, see `attestation::new_ed25519_instruction`.
+    ///
+    /// Then for `FaucetMode::TokenVault`, which pays out `distribution_amount` base units:
+    /// - `[]` Mint
+    /// - `[writable]` Faucet vault
+    /// - `[writable]` Associated token account of the recipient, created if missing
+    /// - `[]` Token program
+    /// - `[]` Associated token account program
+    /// - `[]` Optional SPL Memo program, required when the recipient account requires memos
+    ///
+    /// Transfer fees of Token-2022 mints are added on top, the recipient nets
+    /// `distribution_amount`.
+    ///
+    /// Or for `FaucetMode::MintAuthority`, which mints `distribution_amount` base units:
+    /// - `[writable]` Mint
+    /// - `[writable]` Associated token account of the recipient, created if missing
+    /// - `[]` Token program
+    /// - `[]` Associated token account program
+    RequestTokens { merkle_claim: Option<MerkleClaim> }, // instruction variant
+    /// Moves `replenish_amount` lamports from the admin into the faucet
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[writable, signer]` Admin
+    /// 2. `[]` System program
+    ///
+    /// `FaucetMode::TokenVault` deposits base units into the vault instead and also expects:
+    /// 3. `[]` Mint
+    /// 4. `[writable]` Faucet vault
+    /// 5. `[writable]` Token account of the admin
+    /// 6. `[]` Token program
+    ///
+    /// Not supported by `FaucetMode::MintAuthority`, which mints instead.
+    ReplenishTokens { replenish_amount: u64 },
+    /// Changes the faucet configuration, fields left as `None` are kept
+    ///
+    /// At most `window_cap` is paid out every `window_slots` slots, a zero `window_slots`
+    /// disables the cap and a non-zero one needs a non-zero `window_cap`.
+    /// `recipient_claim_limit` and `recipient_amount_limit` bound what one recipient can ever
+    /// claim, zero means no limit. `allowlist_enabled` restricts claims to wallets added with
+    /// `AddToAllowlist`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    UpdateConfig {
+        distribution_amount: Option<u64>,
+        cooldown_slots: Option<u64>,
+        allow_program_owned_recipients: Option<bool>,
+        window_slots: Option<u64>,
+        window_cap: Option<u64>,
+        recipient_claim_limit: Option<u64>,
+        recipient_amount_limit: Option<u64>,
+        allowlist_enabled: Option<bool>,
+    },
+    /// Proposes `new_admin` as the next admin, replacing any pending proposal
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    ProposeAdmin { new_admin: Pubkey },
+    /// Makes the pending admin the faucet admin
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Pending admin
+    AcceptAdmin,
+    /// Drops the pending admin proposal
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    CancelAdminProposal,
+    /// Moves `amount` lamports from the faucet to a destination, keeping the faucet rent-exempt
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    /// 2. `[writable]` Destination
+    Withdraw { amount: u64 },
+    /// Sends every lamport of the faucet to a destination and wipes its data
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    /// 2. `[writable]` Destination
+    ///
+    /// `FaucetMode::TokenVault` also empties the vault into a token account of the destination
+    /// and closes it, its rent goes to the destination:
+    /// 3. `[]` Mint
+    /// 4. `[writable]` Faucet vault
+    /// 5. `[writable]` Token account receiving the vault tokens
+    /// 6. `[]` Token program
+    /// 7. `[]` Optional SPL Memo program, required when the receiving account requires memos
+    ///
+    /// Token-2022 vaults holding withheld transfer fees can't be closed, harvest them first.
+    ///
+    /// `FaucetMode::MintAuthority` also hands the mint authority to the destination while the
+    /// faucet still holds it:
+    /// 3. `[writable]` Mint
+    /// 4. `[]` Token program
+    CloseFaucet,
+    /// Stops payouts until `Unpause`, admin instructions keep working
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    Pause,
+    /// Resumes payouts
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    Unpause,
+    /// Hands the mint authority of a `FaucetMode::MintAuthority` faucet to `new_authority`,
+    /// which stops the faucet from minting until the authority is given back
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Faucet account
+    /// 1. `[signer]` Admin
+    /// 2. `[writable]` Mint
+    /// 3. `[]` Token program
+    SetMintAuthority { new_authority: Pubkey },
+    /// Changes the lifetime allowance of one recipient. `reset` forgets its past claims,
+    /// `extra_claims` and `extra_amount` replace what it may claim on top of the faucet limits.
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Faucet account
+    /// 1. `[writable, signer]` Admin, pays the claim record rent if it doesn't exist yet
+    /// 2. `[]` Recipient
+    /// 3. `[writable]` Claim record PDA of the recipient
+    /// 4. `[]` System program
+    SetRecipientAllowance {
+        reset: bool,
+        extra_claims: u64,
+        extra_amount: u64,
+    },
+    /// Lets a wallet claim while the allowlist is on
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Faucet account
+    /// 1. `[writable, signer]` Admin, pays the allowlist entry rent
+    /// 2. `[]` Wallet
+    /// 3. `[writable]` Allowlist entry PDA of the wallet, see `find_allowlist_entry_address`
+    /// 4. `[]` System program
+    AddToAllowlist,
+    /// Closes the allowlist entry of a wallet, its rent goes back to the admin
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Faucet account
+    /// 1. `[writable, signer]` Admin
+    /// 2. `[]` Wallet
+    /// 3. `[writable]` Allowlist entry PDA of the wallet
+    RemoveFromAllowlist,
+    /// Sets the root of the Merkle allowlist, `None` turns it off
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    SetMerkleRoot { merkle_root: Option<[u8; 32]> },
+    /// Blocks a wallet from claiming, adding a blocked wallet again is a no-op
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[writable, signer]` Admin, pays the denylist entry rent
+    /// 2. `[]` Wallet
+    /// 3. `[writable]` Denylist entry PDA of the wallet, see `find_denylist_entry_address`
+    /// 4. `[]` System program
+    AddToDenylist,
+    /// Closes the denylist entry of a wallet, its rent goes back to the admin. Removing a wallet
+    /// that isn't blocked is a no-op.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[writable, signer]` Admin
+    /// 2. `[]` Wallet
+    /// 3. `[writable]` Denylist entry PDA of the wallet
+    RemoveFromDenylist,
+    /// Sets the key attesting claims, `None` turns attestations off
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Faucet account
+    /// 1. `[signer]` Admin
+    SetAttester { attester: Option<Pubkey> },
+}
+
+/// Leaf of the recipient in the Merkle allowlist, see `merkle::MerkleTree`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct MerkleClaim {
+    pub amount: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshDeserialize)]
+struct InitializePayload {
+    name: String,
+    distribution_amount: u64,
+    cooldown_slots: u64,
+    allow_program_owned_recipients: bool,
+    mode: FaucetMode,
+}
+#[derive(BorshDeserialize)]
+struct RequestTokensPayload {
+    merkle_claim: Option<MerkleClaim>,
+}
+#[derive(BorshDeserialize)]
+struct ReplenishTokensPayload {
+    replenish_amount: u64,
+}
+#[derive(BorshDeserialize)]
+struct UpdateConfigPayload {
+    distribution_amount: Option<u64>,
+    cooldown_slots: Option<u64>,
+    allow_program_owned_recipients: Option<bool>,
+    window_slots: Option<u64>,
+    window_cap: Option<u64>,
+    recipient_claim_limit: Option<u64>,
+    recipient_amount_limit: Option<u64>,
+    allowlist_enabled: Option<bool>,
+}
+#[derive(BorshDeserialize)]
+struct ProposeAdminPayload {
+    new_admin: Pubkey,
+}
+#[derive(BorshDeserialize)]
+struct WithdrawPayload {
+    amount: u64,
+}
+#[derive(BorshDeserialize)]
+struct SetRecipientAllowancePayload {
+    reset: bool,
+    extra_claims: u64,
+    extra_amount: u64,
+}
+#[derive(BorshDeserialize)]
+struct SetAttesterPayload {
+    attester: Option<Pubkey>,
+}
+#[derive(BorshDeserialize)]
+struct SetMerkleRootPayload {
+    merkle_root: Option<[u8; 32]>,
+}
+#[derive(BorshDeserialize)]
+struct SetMintAuthorityPayload {
+    new_authority: Pubkey,
+}
+
+impl FaucetInstruction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&variant, rest) = input.split_first().ok_or(FaucetError::InvalidInstruction)?;
+
+        Ok(match variant {
+            0 => {
+                let payload = InitializePayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::Initialize {
+                    // same as FaucetInstruction::Initialize {
+                    name: payload.name,
+                    distribution_amount: payload.distribution_amount,
+                    cooldown_slots: payload.cooldown_slots,
+                    allow_program_owned_recipients: payload.allow_program_owned_recipients,
+                    mode: payload.mode,
+                }
+            }
+            // Claims without a Merkle proof may leave the payload out
+            1 if rest.is_empty() => Self::RequestTokens { merkle_claim: None },
+            1 => {
+                let payload = RequestTokensPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::RequestTokens {
+                    merkle_claim: payload.merkle_claim,
+                }
+            }
+            2 => {
+                let payload = ReplenishTokensPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::ReplenishTokens {
+                    replenish_amount: payload.replenish_amount,
+                }
+            }
+            3 => {
+                let payload = UpdateConfigPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::UpdateConfig {
+                    distribution_amount: payload.distribution_amount,
+                    cooldown_slots: payload.cooldown_slots,
+                    allow_program_owned_recipients: payload.allow_program_owned_recipients,
+                    window_slots: payload.window_slots,
+                    window_cap: payload.window_cap,
+                    recipient_claim_limit: payload.recipient_claim_limit,
+                    recipient_amount_limit: payload.recipient_amount_limit,
+                    allowlist_enabled: payload.allowlist_enabled,
+                }
+            }
+            4 => {
+                let payload = ProposeAdminPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::ProposeAdmin {
+                    new_admin: payload.new_admin,
+                }
+            }
+            5 => Self::AcceptAdmin,
+            6 => Self::CancelAdminProposal,
+            7 => {
+                let payload = WithdrawPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::Withdraw {
+                    amount: payload.amount,
+                }
+            }
+            8 => Self::CloseFaucet,
+            9 => Self::Pause,
+            10 => Self::Unpause,
+            11 => {
+                let payload = SetMintAuthorityPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::SetMintAuthority {
+                    new_authority: payload.new_authority,
+                }
+            }
+            12 => {
+                let payload = SetRecipientAllowancePayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::SetRecipientAllowance {
+                    reset: payload.reset,
+                    extra_claims: payload.extra_claims,
+                    extra_amount: payload.extra_amount,
+                }
+            }
+            13 => Self::AddToAllowlist,
+            14 => Self::RemoveFromAllowlist,
+            15 => {
+                let payload = SetMerkleRootPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::SetMerkleRoot {
+                    merkle_root: payload.merkle_root,
+                }
+            }
+            16 => Self::AddToDenylist,
+            17 => Self::RemoveFromDenylist,
+            18 => {
+                let payload = SetAttesterPayload::try_from_slice(rest)
+                    .map_err(|_| FaucetError::InvalidInstruction)?;
+                Self::SetAttester {
+                    attester: payload.attester,
+                }
+            }
+            _ => return Err(FaucetError::InvalidInstruction.into()),
+        })
+    }
+}
+
+/// Fields of `FaucetInstruction::UpdateConfig`, `None` keeps the current value
+#[derive(Clone, Debug, Default)]
+pub struct ConfigUpdate {
+    pub distribution_amount: Option<u64>,
+    pub cooldown_slots: Option<u64>,
+    pub allow_program_owned_recipients: Option<bool>,
+    pub window_slots: Option<u64>,
+    pub window_cap: Option<u64>,
+    pub recipient_claim_limit: Option<u64>,
+    pub recipient_amount_limit: Option<u64>,
+    pub allowlist_enabled: Option<bool>,
+}
+
+/// What a claim brings on top of the accounts its faucet state calls for, see `request_tokens_for`
+#[derive(Clone, Debug, Default)]
+pub struct ClaimOptions {
+    pub merkle_claim: Option<MerkleClaim>, // leaf of the recipient, needed with a Merkle root
+    pub attestation_nonce: Option<u64>,    // needed while an attester is set
+    pub token_program: Option<Pubkey>,     // owner of the faucet mint, needed by token faucets
+}
+
+/// What `request_tokens_for` refuses to build without, nothing reaches the program
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum MissingClaimOption {
+    #[error("Merkle faucets need the leaf of the recipient")]
+    MerkleClaim,
+    #[error("Attested faucets need an attestation nonce")]
+    AttestationNonce,
+    #[error("Token faucets need the token program of their mint")]
+    TokenProgram,
+}
+
+/// `FaucetInstruction::Initialize` of a `FaucetMode::Native` faucet, see `find_faucet_address`
+pub fn initialize(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    name: &str,
+    distribution_amount: u64,
+    cooldown_slots: u64,
+    allow_program_owned_recipients: bool,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::Initialize {
+            name: name.to_string(),
+            distribution_amount,
+            cooldown_slots,
+            allow_program_owned_recipients,
+            mode: FaucetMode::Native,
+        },
+        initialize_accounts(faucet, admin),
+    )
+}
+
+/// `FaucetInstruction::Initialize` of a `FaucetMode::TokenVault` faucet, which creates the
+/// vault of `mint`
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_token_vault(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    name: &str,
+    distribution_amount: u64,
+    cooldown_slots: u64,
+    allow_program_owned_recipients: bool,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let mut accounts = initialize_accounts(faucet, admin);
+    accounts.extend([
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(faucet, mint, token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+    ]);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::Initialize {
+            name: name.to_string(),
+            distribution_amount,
+            cooldown_slots,
+            allow_program_owned_recipients,
+            mode: FaucetMode::TokenVault,
+        },
+        accounts,
+    )
+}
+
+/// `FaucetInstruction::Initialize` of a `FaucetMode::MintAuthority` faucet, the faucet PDA
+/// must already be the mint authority of `mint`
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_mint_authority(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    name: &str,
+    distribution_amount: u64,
+    cooldown_slots: u64,
+    allow_program_owned_recipients: bool,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let mut accounts = initialize_accounts(faucet, admin);
+    accounts.extend([
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ]);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::Initialize {
+            name: name.to_string(),
+            distribution_amount,
+            cooldown_slots,
+            allow_program_owned_recipients,
+            mode: FaucetMode::MintAuthority,
+        },
+        accounts,
+    )
+}
+
+/// `FaucetInstruction::RequestTokens` from a native faucet without any check turned on
+pub fn request_tokens(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::RequestTokens { merkle_claim: None },
+        request_accounts(program_id, faucet, recipient, payer),
+    )
+}
+
+/// `FaucetInstruction::RequestTokens` from a `FaucetMode::TokenVault` faucet without any
+/// check turned on
+pub fn request_tokens_from_vault(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let vault = get_associated_token_address_with_program_id(faucet, mint, token_program);
+    let mut instruction = request_tokens(program_id, faucet, recipient, payer);
+    instruction.accounts.extend(vault_payout_accounts(
+        recipient,
+        mint,
+        &vault,
+        token_program,
+    ));
+    instruction
+}
+
+/// `FaucetInstruction::RequestTokens` from a `FaucetMode::MintAuthority` faucet without any
+/// check turned on
+pub fn request_minted_tokens(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let mut instruction = request_tokens(program_id, faucet, recipient, payer);
+    instruction
+        .accounts
+        .extend(minted_payout_accounts(recipient, mint, token_program));
+    instruction
+}
+
+/// `FaucetInstruction::RequestTokens` with the check and payout accounts `faucet_state` calls for.
+///
+/// While an attester is set the attestation's Ed25519 instruction has to go right before it,
+/// see `attestation::new_ed25519_instruction`.
+///
+/// Fails when `options` lacks what the faucet state calls for and the program would refuse the
+/// claim without: a Merkle claim, an attestation nonce or the token program of the mint.
+pub fn request_tokens_for(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    faucet_state: &FaucetState,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+    options: &ClaimOptions,
+) -> Result<Instruction, MissingClaimOption> {
+    if faucet_state.merkle_root.is_some() && options.merkle_claim.is_none() {
+        return Err(MissingClaimOption::MerkleClaim);
+    }
+    if faucet_state.attester.is_some() && options.attestation_nonce.is_none() {
+        return Err(MissingClaimOption::AttestationNonce);
+    }
+    let token_program = match (faucet_state.mode, options.token_program) {
+        (FaucetMode::Native, _) => None,
+        (_, Some(token_program)) => Some(token_program),
+        (_, None) => return Err(MissingClaimOption::TokenProgram),
+    };
+
+    let mut accounts = request_accounts(program_id, faucet, recipient, payer);
+    if faucet_state.allowlist_enabled {
+        let (allowlist_entry, _) = find_allowlist_entry_address(program_id, faucet, recipient);
+        accounts.push(AccountMeta::new_readonly(allowlist_entry, false));
+    }
+    if let Some(merkle_root) = faucet_state.merkle_root {
+        let (claim_receipt, _) =
+            find_claim_receipt_address(program_id, faucet, &merkle_root, recipient);
+        accounts.push(AccountMeta::new(claim_receipt, false));
+    }
+    if faucet_state.denylist_len > 0 {
+        let (denylist_entry, _) = find_denylist_entry_address(program_id, faucet, recipient);
+        accounts.push(AccountMeta::new_readonly(denylist_entry, false));
+    }
+    if let (Some(_), Some(nonce)) = (faucet_state.attester, options.attestation_nonce) {
+        let (nonce_account, _) = find_attestation_nonce_address(program_id, faucet, nonce);
+        accounts.extend([
+            AccountMeta::new(nonce_account, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+        ]);
+    }
+
+    match (faucet_state.mode, token_program) {
+        (FaucetMode::TokenVault, Some(token_program)) => accounts.extend(vault_payout_accounts(
+            recipient,
+            &faucet_state.mint,
+            &faucet_state.vault,
+            &token_program,
+        )),
+        (FaucetMode::MintAuthority, Some(token_program)) => accounts.extend(
+            minted_payout_accounts(recipient, &faucet_state.mint, &token_program),
+        ),
+        _ => {}
+    }
+
+    Ok(Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::RequestTokens {
+            merkle_claim: options.merkle_claim.clone(),
+        },
+        accounts,
+    ))
+}
+
+/// `FaucetInstruction::ReplenishTokens` of a native faucet
+pub fn replenish(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    replenish_amount: u64,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::ReplenishTokens { replenish_amount },
+        vec![
+            AccountMeta::new(*faucet, false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// `FaucetInstruction::ReplenishTokens` of a `FaucetMode::TokenVault` faucet, the base units
+/// come from the associated token account of the admin
+pub fn replenish_token_vault(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    replenish_amount: u64,
+) -> Instruction {
+    let mut instruction = replenish(program_id, faucet, admin, replenish_amount);
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(faucet, mint, token_program),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(admin, mint, token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(*token_program, false),
+    ]);
+    instruction
+}
+
+pub fn update_config(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    update: ConfigUpdate,
+) -> Instruction {
+    admin_instruction(
+        program_id,
+        faucet,
+        admin,
+        &FaucetInstruction::UpdateConfig {
+            distribution_amount: update.distribution_amount,
+            cooldown_slots: update.cooldown_slots,
+            allow_program_owned_recipients: update.allow_program_owned_recipients,
+            window_slots: update.window_slots,
+            window_cap: update.window_cap,
+            recipient_claim_limit: update.recipient_claim_limit,
+            recipient_amount_limit: update.recipient_amount_limit,
+            allowlist_enabled: update.allowlist_enabled,
+        },
+    )
+}
+
+pub fn propose_admin(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    new_admin: &Pubkey,
+) -> Instruction {
+    admin_instruction(
+        program_id,
+        faucet,
+        admin,
+        &FaucetInstruction::ProposeAdmin {
+            new_admin: *new_admin,
+        },
+    )
+}
+
+pub fn accept_admin(program_id: &Pubkey, faucet: &Pubkey, pending_admin: &Pubkey) -> Instruction {
+    admin_instruction(
+        program_id,
+        faucet,
+        pending_admin,
+        &FaucetInstruction::AcceptAdmin,
+    )
+}
+
+pub fn cancel_admin_proposal(program_id: &Pubkey, faucet: &Pubkey, admin: &Pubkey) -> Instruction {
+    admin_instruction(
+        program_id,
+        faucet,
+        admin,
+        &FaucetInstruction::CancelAdminProposal,
+    )
+}
+
+pub fn withdraw(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut instruction = admin_instruction(
+        program_id,
+        faucet,
+        admin,
+        &FaucetInstruction::Withdraw { amount },
+    );
+    instruction
+        .accounts
+        .push(AccountMeta::new(*destination, false));
+    instruction
+}
+
+pub fn close_faucet(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    let mut instruction =
+        admin_instruction(program_id, faucet, admin, &FaucetInstruction::CloseFaucet);
+    instruction
+        .accounts
+        .push(AccountMeta::new(*destination, false));
+    instruction
+}
+
+/// `FaucetInstruction::CloseFaucet` of a `FaucetMode::TokenVault` faucet, the vault tokens go
+/// to the associated token account of `destination`, which has to exist. Create it idempotently
+/// earlier in the same transaction when it may not.
+pub fn close_token_vault_faucet(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    destination: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let mut instruction = close_faucet(program_id, faucet, admin, destination);
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(faucet, mint, token_program),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(destination, mint, token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_memo::id(), false),
+    ]);
+    instruction
+}
+
+/// `FaucetInstruction::CloseFaucet` of a `FaucetMode::MintAuthority` faucet, the mint authority
+/// goes to `destination`
+pub fn close_mint_authority_faucet(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    destination: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let mut instruction = close_faucet(program_id, faucet, admin, destination);
+    instruction.accounts.extend([
+        AccountMeta::new(*mint, false),
+        AccountMeta::new_readonly(*token_program, false),
+    ]);
+    instruction
+}
+
+pub fn pause(program_id: &Pubkey, faucet: &Pubkey, admin: &Pubkey) -> Instruction {
+    admin_instruction(program_id, faucet, admin, &FaucetInstruction::Pause)
+}
+
+pub fn unpause(program_id: &Pubkey, faucet: &Pubkey, admin: &Pubkey) -> Instruction {
+    admin_instruction(program_id, faucet, admin, &FaucetInstruction::Unpause)
+}
+
+pub fn set_mint_authority(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::SetMintAuthority {
+            new_authority: *new_authority,
+        },
+        vec![
+            AccountMeta::new_readonly(*faucet, false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+    )
+}
+
+pub fn set_recipient_allowance(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    recipient: &Pubkey,
+    reset: bool,
+    extra_claims: u64,
+    extra_amount: u64,
+) -> Instruction {
+    let (claim_record, _) = find_claim_record_address(program_id, faucet, recipient);
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::SetRecipientAllowance {
+            reset,
+            extra_claims,
+            extra_amount,
+        },
+        vec![
+            AccountMeta::new_readonly(*faucet, false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(*recipient, false),
+            AccountMeta::new(claim_record, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn add_to_allowlist(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    wallet: &Pubkey,
+) -> Instruction {
+    let (allowlist_entry, _) = find_allowlist_entry_address(program_id, faucet, wallet);
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::AddToAllowlist,
+        vec![
+            AccountMeta::new_readonly(*faucet, false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new(allowlist_entry, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn remove_from_allowlist(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    wallet: &Pubkey,
+) -> Instruction {
+    let (allowlist_entry, _) = find_allowlist_entry_address(program_id, faucet, wallet);
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::RemoveFromAllowlist,
+        vec![
+            AccountMeta::new_readonly(*faucet, false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new(allowlist_entry, false),
+        ],
+    )
+}
+
+pub fn set_merkle_root(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    merkle_root: Option<[u8; 32]>,
+) -> Instruction {
+    admin_instruction(
+        program_id,
+        faucet,
+        admin,
+        &FaucetInstruction::SetMerkleRoot { merkle_root },
+    )
+}
+
+pub fn add_to_denylist(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    wallet: &Pubkey,
+) -> Instruction {
+    let mut accounts = denylist_accounts(program_id, faucet, admin, wallet);
+    accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    Instruction::new_with_borsh(*program_id, &FaucetInstruction::AddToDenylist, accounts)
+}
+
+pub fn remove_from_denylist(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    wallet: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &FaucetInstruction::RemoveFromDenylist,
+        denylist_accounts(program_id, faucet, admin, wallet),
+    )
+}
+
+pub fn set_attester(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    attester: Option<Pubkey>,
+) -> Instruction {
+    admin_instruction(
+        program_id,
+        faucet,
+        admin,
+        &FaucetInstruction::SetAttester { attester },
+    )
+}
+
+fn initialize_accounts(faucet: &Pubkey, admin: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*faucet, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
+}
+
+fn request_accounts(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+) -> Vec<AccountMeta> {
+    let (claim_record, _) = find_claim_record_address(program_id, faucet, recipient);
+    vec![
+        AccountMeta::new(*faucet, false),
+        AccountMeta::new(*recipient, false),
+        AccountMeta::new(claim_record, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
+}
+
+fn denylist_accounts(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    wallet: &Pubkey,
+) -> Vec<AccountMeta> {
+    let (denylist_entry, _) = find_denylist_entry_address(program_id, faucet, wallet);
+    vec![
+        AccountMeta::new(*faucet, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(*wallet, false),
+        AccountMeta::new(denylist_entry, false),
+    ]
+}
+
+// The memo program is only used when the recipient account requires memos
+fn vault_payout_accounts(
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    vault: &Pubkey,
+    token_program: &Pubkey,
+) -> [AccountMeta; 6] {
+    [
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*vault, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(recipient, mint, token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(spl_memo::id(), false),
+    ]
+}
+
+fn minted_payout_accounts(
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> [AccountMeta; 4] {
+    [
+        AccountMeta::new(*mint, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(recipient, mint, token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+    ]
+}
+
+// Faucet account, then the signing admin
+fn admin_instruction(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    admin: &Pubkey,
+    instruction: &FaucetInstruction,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        instruction,
+        vec![
+            AccountMeta::new(*faucet, false),
+            AccountMeta::new_readonly(*admin, true),
+        ],
+    )
+}
@@ -1,21 +1,39 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint,
     entrypoint::ProgramResult,
+    hash::Hash,
     msg,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction,
+    system_instruction, system_program,
     sysvar::Sysvar,
 };
 
-use instructions::FaucetInstruction;
-use state::FaucetState;
+use error::FaucetError;
+use instruction::{FaucetInstruction, MerkleClaim};
+use state::{
+    find_allowlist_entry_address, find_attestation_nonce_address, find_claim_receipt_address,
+    find_claim_record_address, find_denylist_entry_address, find_faucet_address, AccountType,
+    AllowlistEntry, AttestationNonce, ClaimReceipt, ClaimRecord, DenylistEntry, FaucetMode,
+    FaucetState, ALLOWLIST_SEED, ATTESTATION_NONCE_SEED, CLAIM_RECEIPT_SEED, CLAIM_RECORD_SEED,
+    DENYLIST_SEED, FAUCET_SEED, MAX_FAUCET_NAME_LEN,
+};
 
+pub mod attestation;
+pub mod error;
+pub mod instruction;
+#[deprecated(note = "use `instruction`, which holds both the instructions and their builders")]
 pub mod instructions;
+pub mod merkle;
+#[cfg(not(target_os = "solana"))]
+pub mod rpc;
 pub mod state;
+mod token;
 
 entrypoint!(process_instruction);
 
@@ -26,98 +44,543 @@ pub fn process_instruction(
 ) -> ProgramResult {
     let instruction = FaucetInstruction::unpack(instruction_data)?;
 
+    // Handler errors are returned as-is so clients can tell them apart
     match instruction {
         FaucetInstruction::Initialize {
+            name,
             distribution_amount,
-        } => {
-            process_initialize(program_id, accounts, distribution_amount)
-                .expect("Error processing Initialize instruction");
-        }
-        FaucetInstruction::RequestTokens => {
-            process_request_tokens(program_id, accounts)
-                .expect("Error processing RequestTokens instruction");
+            cooldown_slots,
+            allow_program_owned_recipients,
+            mode,
+        } => process_initialize(
+            program_id,
+            accounts,
+            name,
+            distribution_amount,
+            cooldown_slots,
+            allow_program_owned_recipients,
+            mode,
+        ),
+        FaucetInstruction::RequestTokens { merkle_claim } => {
+            process_request_tokens(program_id, accounts, merkle_claim)
         }
         FaucetInstruction::ReplenishTokens { replenish_amount } => {
             process_replenish_tokens(program_id, accounts, replenish_amount)
-                .expect("Error processing ReplenishTokens instruction");
+        }
+        FaucetInstruction::UpdateConfig {
+            distribution_amount,
+            cooldown_slots,
+            allow_program_owned_recipients,
+            window_slots,
+            window_cap,
+            recipient_claim_limit,
+            recipient_amount_limit,
+            allowlist_enabled,
+        } => process_update_config(
+            program_id,
+            accounts,
+            distribution_amount,
+            cooldown_slots,
+            allow_program_owned_recipients,
+            window_slots,
+            window_cap,
+            recipient_claim_limit,
+            recipient_amount_limit,
+            allowlist_enabled,
+        ),
+        FaucetInstruction::ProposeAdmin { new_admin } => {
+            process_propose_admin(program_id, accounts, new_admin)
+        }
+        FaucetInstruction::AcceptAdmin => process_accept_admin(program_id, accounts),
+        FaucetInstruction::CancelAdminProposal => {
+            process_cancel_admin_proposal(program_id, accounts)
+        }
+        FaucetInstruction::Withdraw { amount } => process_withdraw(program_id, accounts, amount),
+        FaucetInstruction::CloseFaucet => process_close_faucet(program_id, accounts),
+        FaucetInstruction::Pause => process_set_paused(program_id, accounts, true),
+        FaucetInstruction::Unpause => process_set_paused(program_id, accounts, false),
+        FaucetInstruction::SetMintAuthority { new_authority } => {
+            process_set_mint_authority(program_id, accounts, new_authority)
+        }
+        FaucetInstruction::SetRecipientAllowance {
+            reset,
+            extra_claims,
+            extra_amount,
+        } => {
+            process_set_recipient_allowance(program_id, accounts, reset, extra_claims, extra_amount)
+        }
+        FaucetInstruction::AddToAllowlist => process_add_to_allowlist(program_id, accounts),
+        FaucetInstruction::RemoveFromAllowlist => {
+            process_remove_from_allowlist(program_id, accounts)
+        }
+        FaucetInstruction::SetMerkleRoot { merkle_root } => {
+            process_set_merkle_root(program_id, accounts, merkle_root)
+        }
+        FaucetInstruction::AddToDenylist => process_add_to_denylist(program_id, accounts),
+        FaucetInstruction::RemoveFromDenylist => process_remove_from_denylist(program_id, accounts),
+        FaucetInstruction::SetAttester { attester } => {
+            process_set_attester(program_id, accounts, attester)
         }
     }
-
-    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_initialize(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
+    name: String,
     distribution_amount: u64,
+    cooldown_slots: u64,
+    allow_program_owned_recipients: bool,
+    mode: FaucetMode,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
     let faucet_account = next_account_info(accounts_iter)?;
     let admin_account = next_account_info(accounts_iter)?;
-    let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
-
-    // Check if the faucet account is the correct account
-    if faucet_account.owner != program_id {
-        msg!("Faucet account must be owned by the program");
-        return Err(ProgramError::IncorrectProgramId);
-    }
+    let system_program = next_account_info(accounts_iter)?;
 
-    // Check if the admin account is a signer
+    // Check if the admin account is a signer, it also pays the faucet rent
     if !admin_account.is_signer {
         msg!("Admin account must be a signer");
-        return Err(ProgramError::MissingRequiredSignature);
+        return Err(FaucetError::MissingSignature.into());
+    }
+
+    if name.len() > MAX_FAUCET_NAME_LEN {
+        msg!(
+            "Faucet name can't be longer than {} bytes",
+            MAX_FAUCET_NAME_LEN
+        );
+        return Err(FaucetError::InvalidFaucetName.into());
     }
 
-    if !rent.is_exempt(faucet_account.lamports(), faucet_account.data_len()) {
-        msg!("Faucet account lamports is below rent-exempt threshold");
-        return Err(ProgramError::AccountNotRentExempt);
+    // Check if the faucet account is the PDA derived from the admin and name
+    let (faucet_address, faucet_bump) = find_faucet_address(program_id, admin_account.key, &name);
+    if *faucet_account.key != faucet_address {
+        msg!("Faucet account must be the faucet address of the admin and name");
+        return Err(FaucetError::InvalidFaucetAddress.into());
     }
 
+    // Refuse to overwrite a live faucet, only Initialize gives the PDA to the program
+    if faucet_account.owner == program_id {
+        msg!("Faucet account is already initialized");
+        return Err(FaucetError::AlreadyInitialized.into());
+    }
+
+    create_pda_account(
+        admin_account,
+        faucet_account,
+        system_program,
+        program_id,
+        FaucetState::LEN,
+        &[
+            FAUCET_SEED,
+            admin_account.key.as_ref(),
+            name.as_bytes(),
+            &[faucet_bump],
+        ],
+    )?;
+
+    // Token faucets hold their tokens in the associated token account of the faucet PDA
+    let (mint, vault) = match mode {
+        FaucetMode::Native => (Pubkey::default(), Pubkey::default()),
+        FaucetMode::TokenVault => {
+            let mint_account = next_account_info(accounts_iter)?;
+            let vault_account = next_account_info(accounts_iter)?;
+            let token_program = next_account_info(accounts_iter)?;
+            let ata_program = next_account_info(accounts_iter)?;
+
+            token::check_token_program(token_program)?;
+            token::check_associated_token_program(ata_program)?;
+            token::mint_decimals(mint_account, token_program)?;
+            token::check_associated_token_account(
+                vault_account,
+                faucet_account.key,
+                mint_account.key,
+                token_program.key,
+            )?;
+
+            token::create_associated_token_account(
+                admin_account,
+                vault_account,
+                faucet_account,
+                mint_account,
+                system_program,
+                token_program,
+                ata_program,
+            )?;
+
+            (*mint_account.key, *vault_account.key)
+        }
+        FaucetMode::MintAuthority => {
+            let mint_account = next_account_info(accounts_iter)?;
+            let token_program = next_account_info(accounts_iter)?;
+
+            token::check_token_program(token_program)?;
+            let mint_state = token::load_mint(mint_account, token_program)?;
+            token::check_mint_authority(&mint_state, faucet_account.key)?;
+
+            (*mint_account.key, Pubkey::default())
+        }
+    };
+
     let faucet_state = FaucetState {
+        account_type: AccountType::Faucet,
+        bump: faucet_bump,
+        creator: *admin_account.key,
         admin: *admin_account.key, // * dereferences &Pubkey to Pubkey
         distribution_amount,
+        cooldown_slots,
+        allow_program_owned_recipients,
+        paused: false,
+        pending_admin: None,
+        mode,
+        mint,
+        vault,
+        name,
+        window_slots: 0,
+        window_cap: 0,
+        window_start_slot: 0,
+        window_distributed: 0,
+        recipient_claim_limit: 0,
+        recipient_amount_limit: 0,
+        allowlist_enabled: false,
+        merkle_root: None,
+        denylist_len: 0,
+        attester: None,
     };
 
-    faucet_state.serialize(&mut &mut faucet_account.data.borrow_mut()[..])?;
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
 
     msg!(
-        "Faucet initialized. Admin: {}, Distribution Amount: {}",
+        "Faucet {:?} initialized. Admin: {}, Distribution Amount: {}, Cooldown Slots: {}, \
+         Mode: {:?}",
+        faucet_state.name,
         faucet_state.admin,
-        faucet_state.distribution_amount
+        faucet_state.distribution_amount,
+        faucet_state.cooldown_slots,
+        faucet_state.mode
     );
 
     Ok(())
 }
 
-pub fn process_request_tokens(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    if accounts.len() != 3 {
-        msg!("Incorrect number of accounts");
-        return Err(ProgramError::InvalidAccountData);
-    }
-
+pub fn process_request_tokens(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    merkle_claim: Option<MerkleClaim>,
+) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
     let faucet_account = next_account_info(accounts_iter)?;
     let user_account = next_account_info(accounts_iter)?;
+    let claim_record_account = next_account_info(accounts_iter)?;
+    let payer_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
 
-    // Check if the faucet account is the correct account
-    if faucet_account.owner != program_id {
-        msg!("Faucet account must be owned by the program");
-        return Err(ProgramError::IncorrectProgramId);
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+
+    // Accounts of the enabled checks come first, then the mode accounts
+    let check_accounts = usize::from(faucet_state.allowlist_enabled)
+        + usize::from(faucet_state.merkle_root.is_some())
+        + usize::from(faucet_state.denylist_len > 0)
+        + 2 * usize::from(faucet_state.attester.is_some());
+    let (min_mode_accounts, max_mode_accounts) = match faucet_state.mode {
+        FaucetMode::Native => (0, 0),
+        FaucetMode::TokenVault => (5, 6), // the memo program is optional
+        FaucetMode::MintAuthority => (4, 4),
+    };
+    let expected_accounts =
+        5 + check_accounts + min_mode_accounts..=5 + check_accounts + max_mode_accounts;
+    if !expected_accounts.contains(&accounts.len()) {
+        msg!("Incorrect number of accounts");
+        return Err(FaucetError::IncorrectAccountCount.into());
+    }
+
+    if faucet_state.paused {
+        msg!("Faucet is paused");
+        return Err(FaucetError::FaucetPaused.into());
+    }
+
+    // The payer signs the claim, which lets a relayer claim for an empty wallet
+    if !payer_account.is_signer {
+        msg!("Payer account must be a signer");
+        return Err(FaucetError::MissingSignature.into());
+    }
+
+    if user_account.key == faucet_account.key {
+        msg!("Faucet can't pay out to itself");
+        return Err(FaucetError::InvalidRecipient.into());
+    }
+
+    if !user_account.is_writable {
+        msg!("Recipient account must be writable");
+        return Err(FaucetError::InvalidRecipient.into());
+    }
+
+    if user_account.owner != &system_program::id() && !faucet_state.allow_program_owned_recipients {
+        msg!("Recipient account must be owned by the system program");
+        return Err(FaucetError::InvalidRecipient.into());
+    }
+
+    if faucet_state.allowlist_enabled {
+        let allowlist_entry_account = next_account_info(accounts_iter)?;
+        check_allowlist_entry(
+            program_id,
+            faucet_account,
+            user_account,
+            allowlist_entry_account,
+        )?;
     }
 
-    let faucet_state = FaucetState::try_from_slice(&faucet_account.data.borrow())
-        .expect("Failed to deserialize FaucetState");
+    // Merkle leaves carry their own amount, everyone else gets `distribution_amount`
+    let transfer_amount = match faucet_state.merkle_root {
+        Some(merkle_root) => {
+            let claim_receipt_account = next_account_info(accounts_iter)?;
+            claim_merkle_leaf(
+                program_id,
+                faucet_account,
+                user_account,
+                claim_receipt_account,
+                payer_account,
+                system_program,
+                &merkle_root,
+                merkle_claim,
+            )?
+        }
+        None => faucet_state.distribution_amount,
+    };
+
+    if faucet_state.denylist_len > 0 {
+        let denylist_entry_account = next_account_info(accounts_iter)?;
+        if is_denylisted(
+            program_id,
+            faucet_account,
+            user_account,
+            denylist_entry_account,
+        )? {
+            msg!("{} is on the denylist", user_account.key);
+            return Err(FaucetError::Denylisted.into());
+        }
+    }
+
+    if let Some(attester) = faucet_state.attester {
+        let nonce_account = next_account_info(accounts_iter)?;
+        let instructions_sysvar = next_account_info(accounts_iter)?;
+        check_attestation(
+            program_id,
+            faucet_account,
+            user_account,
+            nonce_account,
+            instructions_sysvar,
+            payer_account,
+            system_program,
+            &attester,
+            transfer_amount,
+        )?;
+    }
+
+    let (claim_record_address, claim_record_bump) =
+        find_claim_record_address(program_id, faucet_account.key, user_account.key);
+    if *claim_record_account.key != claim_record_address {
+        msg!("Claim record account does not match the faucet and recipient");
+        return Err(FaucetError::InvalidClaimRecord.into());
+    }
+
+    let current_slot = Clock::get()?.slot;
+
+    let mut claim_record = if claim_record_account.owner == program_id {
+        // Recipient claimed before, make sure the cooldown window is over
+        let claim_record = load_claim_record(claim_record_account)?;
+        let next_claim_slot = claim_record
+            .last_claim_slot
+            .saturating_add(faucet_state.cooldown_slots);
+
+        if current_slot < next_claim_slot {
+            msg!(
+                "Recipient is in cooldown. Next claim possible at slot {}",
+                next_claim_slot
+            );
+            return Err(FaucetError::CooldownActive.into());
+        }
+
+        claim_record
+    } else {
+        // First claim, the payer funds the claim record
+        create_pda_account(
+            payer_account,
+            claim_record_account,
+            system_program,
+            program_id,
+            ClaimRecord::LEN,
+            &[
+                CLAIM_RECORD_SEED,
+                faucet_account.key.as_ref(),
+                user_account.key.as_ref(),
+                &[claim_record_bump],
+            ],
+        )?;
+
+        ClaimRecord {
+            account_type: AccountType::ClaimRecord,
+            ..ClaimRecord::default()
+        }
+    };
+
+    if !claim_record.can_claim(&faucet_state, transfer_amount) {
+        msg!(
+            "Recipient already claimed {} times for a total of {}",
+            claim_record.claim_count,
+            claim_record.total_claimed
+        );
+        return Err(FaucetError::ClaimLimitReached.into());
+    }
+
+    // The window cap bounds what many fresh recipients can drain together
+    if faucet_state.window_slots > 0 {
+        let window_end_slot = faucet_state
+            .window_start_slot
+            .saturating_add(faucet_state.window_slots);
+        if current_slot >= window_end_slot {
+            faucet_state.window_start_slot = current_slot;
+            faucet_state.window_distributed = 0;
+        }
+
+        let window_distributed = faucet_state
+            .window_distributed
+            .checked_add(transfer_amount)
+            .ok_or(FaucetError::AmountOverflow)?;
+        if window_distributed > faucet_state.window_cap {
+            msg!(
+                "Window cap reached. Next window starts at slot {}",
+                faucet_state
+                    .window_start_slot
+                    .saturating_add(faucet_state.window_slots)
+            );
+            return Err(FaucetError::WindowCapReached.into());
+        }
+
+        faucet_state.window_distributed = window_distributed;
+        faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+    }
+
+    match faucet_state.mode {
+        FaucetMode::Native => pay_out_lamports(faucet_account, user_account, transfer_amount)?,
+        FaucetMode::TokenVault => {
+            let mint_account = next_account_info(accounts_iter)?;
+            let vault_account = next_account_info(accounts_iter)?;
+            let user_token_account = next_account_info(accounts_iter)?;
+            let token_program = next_account_info(accounts_iter)?;
+            let ata_program = next_account_info(accounts_iter)?;
+            let memo_program = next_account_info(accounts_iter).ok();
+
+            token::check_token_program(token_program)?;
+            token::check_associated_token_program(ata_program)?;
+            check_token_accounts(&faucet_state, mint_account, vault_account)?;
+            let decimals = token::mint_decimals(mint_account, token_program)?;
+            token::check_associated_token_account(
+                user_token_account,
+                user_account.key,
+                mint_account.key,
+                token_program.key,
+            )?;
+
+            // Transfer fees come on top so the recipient gets the full amount
+            let (gross_amount, fee) = token::gross_transfer_amount(mint_account, transfer_amount)?;
+
+            if gross_amount > token::token_balance(vault_account)? {
+                msg!("Faucet vault can't cover {} tokens", gross_amount);
+                return Err(FaucetError::InsufficientFunds.into());
+            }
+
+            // The payer also covers the recipient's token account if it is missing
+            token::create_associated_token_account(
+                payer_account,
+                user_token_account,
+                user_account,
+                mint_account,
+                system_program,
+                token_program,
+                ata_program,
+            )?;
+
+            if token::requires_memo(user_token_account)? {
+                token::memo(memo_program, "Faucet payout")?;
+            }
 
-    let transfer_amount = faucet_state.distribution_amount;
+            token::transfer(
+                token_program,
+                vault_account,
+                mint_account,
+                user_token_account,
+                faucet_account,
+                gross_amount,
+                decimals,
+                fee,
+                &[&faucet_state.signer_seeds()],
+            )?;
 
-    **faucet_account.try_borrow_mut_lamports()? -= transfer_amount;
-    **user_account.try_borrow_mut_lamports()? += transfer_amount;
+            if let Some(fee) = fee {
+                msg!("Transfer fee of {} tokens withheld", fee);
+            }
+        }
+        FaucetMode::MintAuthority => {
+            let mint_account = next_account_info(accounts_iter)?;
+            let user_token_account = next_account_info(accounts_iter)?;
+            let token_program = next_account_info(accounts_iter)?;
+            let ata_program = next_account_info(accounts_iter)?;
+
+            token::check_token_program(token_program)?;
+            token::check_associated_token_program(ata_program)?;
+            check_faucet_mint(&faucet_state, mint_account)?;
+            token::check_associated_token_account(
+                user_token_account,
+                user_account.key,
+                mint_account.key,
+                token_program.key,
+            )?;
+
+            // Minting stops while the admin holds the mint authority
+            let mint_state = token::load_mint(mint_account, token_program)?;
+            token::check_mint_authority(&mint_state, faucet_account.key)?;
+
+            token::create_associated_token_account(
+                payer_account,
+                user_token_account,
+                user_account,
+                mint_account,
+                system_program,
+                token_program,
+                ata_program,
+            )?;
+
+            token::mint_to(
+                token_program,
+                mint_account,
+                user_token_account,
+                faucet_account,
+                transfer_amount,
+                mint_state.decimals,
+                &[&faucet_state.signer_seeds()],
+            )?;
+        }
+    }
+
+    claim_record.last_claim_slot = current_slot;
+    claim_record.claim_count = claim_record.claim_count.saturating_add(1);
+    claim_record.total_claimed = claim_record.total_claimed.saturating_add(transfer_amount);
+    claim_record
+        .serialize(&mut &mut claim_record_account.data.borrow_mut()[..])
+        .map_err(|_| FaucetError::InvalidClaimRecord)?;
 
     msg!(
-        "Transferred {} lamports to {}",
+        "Transferred {} {} to {}",
         transfer_amount,
+        if faucet_state.mode == FaucetMode::Native {
+            "lamports"
+        } else {
+            "tokens"
+        },
         user_account.key
     );
 
@@ -135,21 +598,43 @@ fn process_replenish_tokens(
     let admin_account = next_account_info(accounts_iter)?;
     let system_program = next_account_info(accounts_iter)?;
 
-    if faucet_account.owner != program_id {
-        msg!("Faucet account must be owned by the program");
-        return Err(ProgramError::IncorrectProgramId);
-    }
+    let faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
 
-    if !admin_account.is_signer {
-        msg!("Admin account must be a signer");
-        return Err(ProgramError::MissingRequiredSignature);
+    if faucet_state.mode == FaucetMode::MintAuthority {
+        msg!("Mint authority faucets mint on demand and can't be replenished");
+        return Err(FaucetError::UnsupportedFaucetMode.into());
     }
 
-    let faucet_state = FaucetState::try_from_slice(&mut &faucet_account.data.borrow())?;
+    if faucet_state.mode == FaucetMode::TokenVault {
+        let mint_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let admin_token_account = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
 
-    if faucet_state.admin != *admin_account.key {
-        msg!("Admin account must be the faucet admin");
-        return Err(ProgramError::InvalidAccountData);
+        token::check_token_program(token_program)?;
+        check_token_accounts(&faucet_state, mint_account, vault_account)?;
+
+        let decimals = token::mint_decimals(mint_account, token_program)?;
+        token::transfer(
+            token_program,
+            admin_token_account,
+            mint_account,
+            vault_account,
+            admin_account,
+            replenish_amount,
+            decimals,
+            None,
+            &[],
+        )?;
+
+        msg!(
+            "Deposited {} tokens into vault {}",
+            replenish_amount,
+            vault_account.key,
+        );
+
+        return Ok(());
     }
 
     // Create the transfer instruction
@@ -157,7 +642,7 @@ fn process_replenish_tokens(
         system_instruction::transfer(admin_account.key, faucet_account.key, replenish_amount);
 
     // Invoke the transfer instruction
-    solana_program::program::invoke(
+    invoke(
         &transfer_instruction,
         &[
             admin_account.clone(),
@@ -174,3 +659,990 @@ fn process_replenish_tokens(
 
     Ok(())
 }
+
+#[allow(clippy::too_many_arguments)]
+fn process_update_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    distribution_amount: Option<u64>,
+    cooldown_slots: Option<u64>,
+    allow_program_owned_recipients: Option<bool>,
+    window_slots: Option<u64>,
+    window_cap: Option<u64>,
+    recipient_claim_limit: Option<u64>,
+    recipient_amount_limit: Option<u64>,
+    allowlist_enabled: Option<bool>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    // Only the supplied fields change
+    if let Some(distribution_amount) = distribution_amount {
+        msg!(
+            "Distribution amount: {} -> {}",
+            faucet_state.distribution_amount,
+            distribution_amount
+        );
+        faucet_state.distribution_amount = distribution_amount;
+    }
+
+    if let Some(cooldown_slots) = cooldown_slots {
+        msg!(
+            "Cooldown slots: {} -> {}",
+            faucet_state.cooldown_slots,
+            cooldown_slots
+        );
+        faucet_state.cooldown_slots = cooldown_slots;
+    }
+
+    if let Some(allow_program_owned_recipients) = allow_program_owned_recipients {
+        msg!(
+            "Allow program-owned recipients: {} -> {}",
+            faucet_state.allow_program_owned_recipients,
+            allow_program_owned_recipients
+        );
+        faucet_state.allow_program_owned_recipients = allow_program_owned_recipients;
+    }
+
+    if let Some(window_slots) = window_slots {
+        msg!(
+            "Window slots: {} -> {}",
+            faucet_state.window_slots,
+            window_slots
+        );
+        faucet_state.window_slots = window_slots;
+    }
+
+    if let Some(window_cap) = window_cap {
+        msg!("Window cap: {} -> {}", faucet_state.window_cap, window_cap);
+        faucet_state.window_cap = window_cap;
+    }
+
+    if let Some(recipient_claim_limit) = recipient_claim_limit {
+        msg!(
+            "Recipient claim limit: {} -> {}",
+            faucet_state.recipient_claim_limit,
+            recipient_claim_limit
+        );
+        faucet_state.recipient_claim_limit = recipient_claim_limit;
+    }
+
+    if let Some(recipient_amount_limit) = recipient_amount_limit {
+        msg!(
+            "Recipient amount limit: {} -> {}",
+            faucet_state.recipient_amount_limit,
+            recipient_amount_limit
+        );
+        faucet_state.recipient_amount_limit = recipient_amount_limit;
+    }
+
+    if let Some(allowlist_enabled) = allowlist_enabled {
+        msg!(
+            "Allowlist enabled: {} -> {}",
+            faucet_state.allowlist_enabled,
+            allowlist_enabled
+        );
+        faucet_state.allowlist_enabled = allowlist_enabled;
+    }
+
+    if faucet_state.window_slots > 0 && faucet_state.window_cap == 0 {
+        msg!("A distribution window needs a non-zero window cap");
+        return Err(FaucetError::InvalidWindowConfig.into());
+    }
+
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_propose_admin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_admin: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    // The admin only moves once the new admin proves it holds the key
+    faucet_state.pending_admin = Some(new_admin);
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    msg!("Proposed {} as the new faucet admin", new_admin);
+
+    Ok(())
+}
+
+fn process_accept_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let new_admin_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+
+    if !new_admin_account.is_signer {
+        msg!("Pending admin account must be a signer");
+        return Err(FaucetError::MissingSignature.into());
+    }
+
+    if faucet_state.pending_admin != Some(*new_admin_account.key) {
+        msg!("Signer is not the pending faucet admin");
+        return Err(FaucetError::NotPendingAdmin.into());
+    }
+
+    msg!(
+        "Faucet admin: {} -> {}",
+        faucet_state.admin,
+        new_admin_account.key
+    );
+
+    faucet_state.admin = *new_admin_account.key;
+    faucet_state.pending_admin = None;
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_cancel_admin_proposal(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    let Some(pending_admin) = faucet_state.pending_admin.take() else {
+        msg!("There is no pending admin proposal");
+        return Err(FaucetError::NotPendingAdmin.into());
+    };
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    msg!("Cancelled the admin proposal for {}", pending_admin);
+
+    Ok(())
+}
+
+fn process_withdraw(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let destination_account = next_account_info(accounts_iter)?;
+
+    let faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    if destination_account.key == faucet_account.key {
+        msg!("Destination can't be the faucet itself");
+        return Err(FaucetError::InvalidDestination.into());
+    }
+
+    // Closing the faucet is the only way below the rent-exempt reserve
+    pay_out_lamports(faucet_account, destination_account, amount)?;
+
+    msg!(
+        "Withdrew {} lamports to {}",
+        amount,
+        destination_account.key
+    );
+
+    Ok(())
+}
+
+fn process_close_faucet(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let destination_account = next_account_info(accounts_iter)?;
+
+    let faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    if destination_account.key == faucet_account.key {
+        msg!("Destination can't be the faucet itself");
+        return Err(FaucetError::InvalidDestination.into());
+    }
+
+    // Nothing can move the vault tokens or sign for the mint once the faucet is gone
+    match faucet_state.mode {
+        FaucetMode::Native => {}
+        FaucetMode::TokenVault => {
+            let mint_account = next_account_info(accounts_iter)?;
+            let vault_account = next_account_info(accounts_iter)?;
+            let destination_token_account = next_account_info(accounts_iter)?;
+            let token_program = next_account_info(accounts_iter)?;
+            let memo_program = next_account_info(accounts_iter).ok();
+
+            token::check_token_program(token_program)?;
+            check_token_accounts(&faucet_state, mint_account, vault_account)?;
+            let decimals = token::mint_decimals(mint_account, token_program)?;
+
+            let vault_balance = token::token_balance(vault_account)?;
+            if vault_balance > 0 {
+                if token::requires_memo(destination_token_account)? {
+                    token::memo(memo_program, "Faucet closed")?;
+                }
+
+                // The transfer fee comes out of the vault balance
+                let fee = token::transfer_fee(mint_account, vault_balance)?;
+                token::transfer(
+                    token_program,
+                    vault_account,
+                    mint_account,
+                    destination_token_account,
+                    faucet_account,
+                    vault_balance,
+                    decimals,
+                    fee,
+                    &[&faucet_state.signer_seeds()],
+                )?;
+            }
+
+            token::close_account(
+                token_program,
+                vault_account,
+                destination_account,
+                faucet_account,
+                &[&faucet_state.signer_seeds()],
+            )?;
+
+            msg!(
+                "Moved {} tokens to {} and closed the vault",
+                vault_balance,
+                destination_token_account.key
+            );
+        }
+        FaucetMode::MintAuthority => {
+            let mint_account = next_account_info(accounts_iter)?;
+            let token_program = next_account_info(accounts_iter)?;
+
+            token::check_token_program(token_program)?;
+            check_faucet_mint(&faucet_state, mint_account)?;
+
+            let mint_state = token::load_mint(mint_account, token_program)?;
+            if token::is_mint_authority(&mint_state, faucet_account.key) {
+                token::set_mint_authority(
+                    token_program,
+                    mint_account,
+                    faucet_account,
+                    destination_account.key,
+                    &[&faucet_state.signer_seeds()],
+                )?;
+
+                msg!(
+                    "Mint authority of {} handed to {}",
+                    mint_account.key,
+                    destination_account.key
+                );
+            }
+        }
+    }
+
+    let faucet_lamports = faucet_account.lamports();
+    close_program_account(faucet_account, destination_account)?;
+
+    msg!(
+        "Closed faucet {}, sent {} lamports to {}",
+        faucet_account.key,
+        faucet_lamports,
+        destination_account.key
+    );
+
+    Ok(())
+}
+
+fn process_set_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    paused: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    faucet_state.paused = paused;
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    msg!("Faucet {}", if paused { "paused" } else { "unpaused" });
+
+    Ok(())
+}
+
+fn process_set_mint_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_authority: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let mint_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    let faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    if faucet_state.mode != FaucetMode::MintAuthority {
+        msg!("Only mint authority faucets hold a mint authority");
+        return Err(FaucetError::UnsupportedFaucetMode.into());
+    }
+
+    token::check_token_program(token_program)?;
+    check_faucet_mint(&faucet_state, mint_account)?;
+
+    token::set_mint_authority(
+        token_program,
+        mint_account,
+        faucet_account,
+        &new_authority,
+        &[&faucet_state.signer_seeds()],
+    )?;
+
+    msg!(
+        "Mint authority of {} handed to {}",
+        mint_account.key,
+        new_authority
+    );
+
+    Ok(())
+}
+
+fn process_set_recipient_allowance(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    reset: bool,
+    extra_claims: u64,
+    extra_amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let recipient_account = next_account_info(accounts_iter)?;
+    let claim_record_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    let faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    let (claim_record_address, claim_record_bump) =
+        find_claim_record_address(program_id, faucet_account.key, recipient_account.key);
+    if *claim_record_account.key != claim_record_address {
+        msg!("Claim record account does not match the faucet and recipient");
+        return Err(FaucetError::InvalidClaimRecord.into());
+    }
+
+    // Allowances can be granted before the first claim
+    let mut claim_record = if claim_record_account.owner == program_id {
+        load_claim_record(claim_record_account)?
+    } else {
+        create_pda_account(
+            admin_account,
+            claim_record_account,
+            system_program,
+            program_id,
+            ClaimRecord::LEN,
+            &[
+                CLAIM_RECORD_SEED,
+                faucet_account.key.as_ref(),
+                recipient_account.key.as_ref(),
+                &[claim_record_bump],
+            ],
+        )?;
+
+        ClaimRecord {
+            account_type: AccountType::ClaimRecord,
+            ..ClaimRecord::default()
+        }
+    };
+
+    if reset {
+        claim_record.claim_count = 0;
+        claim_record.total_claimed = 0;
+    }
+    claim_record.extra_claims = extra_claims;
+    claim_record.extra_amount = extra_amount;
+
+    claim_record
+        .serialize(&mut &mut claim_record_account.data.borrow_mut()[..])
+        .map_err(|_| FaucetError::InvalidClaimRecord)?;
+
+    msg!(
+        "Allowance of {}: {} extra claims, {} extra amount{}",
+        recipient_account.key,
+        extra_claims,
+        extra_amount,
+        if reset { ", past claims reset" } else { "" }
+    );
+
+    Ok(())
+}
+
+fn process_add_to_allowlist(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let wallet_account = next_account_info(accounts_iter)?;
+    let allowlist_entry_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    let faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    let (allowlist_entry_address, allowlist_entry_bump) =
+        find_allowlist_entry_address(program_id, faucet_account.key, wallet_account.key);
+    if *allowlist_entry_account.key != allowlist_entry_address {
+        msg!("Allowlist entry account does not match the faucet and wallet");
+        return Err(FaucetError::InvalidAllowlistEntry.into());
+    }
+
+    // Adding a listed wallet again is a no-op
+    if allowlist_entry_account.owner != program_id {
+        create_pda_account(
+            admin_account,
+            allowlist_entry_account,
+            system_program,
+            program_id,
+            AllowlistEntry::LEN,
+            &[
+                ALLOWLIST_SEED,
+                faucet_account.key.as_ref(),
+                wallet_account.key.as_ref(),
+                &[allowlist_entry_bump],
+            ],
+        )?;
+    }
+
+    AllowlistEntry {
+        account_type: AccountType::AllowlistEntry,
+    }
+    .serialize(&mut &mut allowlist_entry_account.data.borrow_mut()[..])
+    .map_err(|_| FaucetError::InvalidAllowlistEntry)?;
+
+    msg!("Added {} to the allowlist", wallet_account.key);
+
+    Ok(())
+}
+
+fn process_remove_from_allowlist(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let wallet_account = next_account_info(accounts_iter)?;
+    let allowlist_entry_account = next_account_info(accounts_iter)?;
+
+    let faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+    check_allowlist_entry(
+        program_id,
+        faucet_account,
+        wallet_account,
+        allowlist_entry_account,
+    )?;
+
+    close_program_account(allowlist_entry_account, admin_account)?;
+
+    msg!("Removed {} from the allowlist", wallet_account.key);
+
+    Ok(())
+}
+
+fn process_set_merkle_root(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    merkle_root: Option<[u8; 32]>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    faucet_state.merkle_root = merkle_root;
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    match merkle_root {
+        Some(merkle_root) => msg!("Merkle root set to {}", Hash::new_from_array(merkle_root)),
+        None => msg!("Merkle root cleared"),
+    }
+
+    Ok(())
+}
+
+fn process_add_to_denylist(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let wallet_account = next_account_info(accounts_iter)?;
+    let denylist_entry_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    // Blocking a blocked wallet again is a no-op, it would count the wallet twice
+    if is_denylisted(
+        program_id,
+        faucet_account,
+        wallet_account,
+        denylist_entry_account,
+    )? {
+        msg!("{} is already on the denylist", wallet_account.key);
+        return Ok(());
+    }
+
+    let (_, denylist_entry_bump) =
+        find_denylist_entry_address(program_id, faucet_account.key, wallet_account.key);
+    create_pda_account(
+        admin_account,
+        denylist_entry_account,
+        system_program,
+        program_id,
+        DenylistEntry::LEN,
+        &[
+            DENYLIST_SEED,
+            faucet_account.key.as_ref(),
+            wallet_account.key.as_ref(),
+            &[denylist_entry_bump],
+        ],
+    )?;
+
+    DenylistEntry {
+        account_type: AccountType::DenylistEntry,
+    }
+    .serialize(&mut &mut denylist_entry_account.data.borrow_mut()[..])
+    .map_err(|_| FaucetError::InvalidDenylistEntry)?;
+
+    faucet_state.denylist_len = faucet_state.denylist_len.saturating_add(1);
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    msg!("Added {} to the denylist", wallet_account.key);
+
+    Ok(())
+}
+
+fn process_remove_from_denylist(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let wallet_account = next_account_info(accounts_iter)?;
+    let denylist_entry_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    // Lifting the block of a wallet that isn't blocked is a no-op, like adding one twice
+    if !is_denylisted(
+        program_id,
+        faucet_account,
+        wallet_account,
+        denylist_entry_account,
+    )? {
+        msg!("{} is not on the denylist", wallet_account.key);
+        return Ok(());
+    }
+
+    close_program_account(denylist_entry_account, admin_account)?;
+
+    faucet_state.denylist_len = faucet_state.denylist_len.saturating_sub(1);
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    msg!("Removed {} from the denylist", wallet_account.key);
+
+    Ok(())
+}
+
+fn process_set_attester(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    attester: Option<Pubkey>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let faucet_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+
+    let mut faucet_state = load_faucet_state(program_id, faucet_account)?;
+    check_admin(&faucet_state, admin_account)?;
+
+    msg!("Attester: {:?} -> {:?}", faucet_state.attester, attester);
+    faucet_state.attester = attester;
+    faucet_state.pack(&mut faucet_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+// Checks the faucet account and decodes its state, refusing uninitialized faucets
+fn load_faucet_state(
+    program_id: &Pubkey,
+    faucet_account: &AccountInfo,
+) -> Result<FaucetState, ProgramError> {
+    if faucet_account.owner != program_id {
+        msg!("Faucet account must be owned by the program");
+        return Err(FaucetError::IncorrectOwner.into());
+    }
+
+    let faucet_state = FaucetState::unpack(&faucet_account.data.borrow())?;
+
+    if !faucet_state.is_initialized() {
+        msg!("Faucet account is not initialized");
+        return Err(FaucetError::UninitializedFaucet.into());
+    }
+
+    Ok(faucet_state)
+}
+
+// Decodes a claim record the program already owns
+fn load_claim_record(claim_record_account: &AccountInfo) -> Result<ClaimRecord, ProgramError> {
+    let claim_record = ClaimRecord::try_from_slice(&claim_record_account.data.borrow())
+        .map_err(|_| FaucetError::InvalidClaimRecord)?;
+    if claim_record.account_type != AccountType::ClaimRecord {
+        msg!("Claim record account has the wrong type");
+        return Err(FaucetError::InvalidClaimRecord.into());
+    }
+
+    Ok(claim_record)
+}
+
+// Checks the attestation of the claim and burns its nonce
+#[allow(clippy::too_many_arguments)]
+fn check_attestation<'a>(
+    program_id: &Pubkey,
+    faucet_account: &AccountInfo<'a>,
+    recipient_account: &AccountInfo<'a>,
+    nonce_account: &AccountInfo<'a>,
+    instructions_sysvar: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    attester: &Pubkey,
+    amount: u64,
+) -> ProgramResult {
+    let attestation = attestation::load_attestation(instructions_sysvar, attester)?;
+    if attestation.faucet != *faucet_account.key
+        || attestation.recipient != *recipient_account.key
+        || attestation.amount != amount
+    {
+        msg!(
+            "Attestation is for {} claiming {} from {}",
+            attestation.recipient,
+            attestation.amount,
+            attestation.faucet
+        );
+        return Err(FaucetError::InvalidAttestation.into());
+    }
+
+    if Clock::get()?.slot > attestation.expiry_slot {
+        msg!("Attestation expired at slot {}", attestation.expiry_slot);
+        return Err(FaucetError::AttestationExpired.into());
+    }
+
+    let (nonce_address, nonce_bump) =
+        find_attestation_nonce_address(program_id, faucet_account.key, attestation.nonce);
+    if *nonce_account.key != nonce_address {
+        msg!("Nonce account does not match the faucet and attestation nonce");
+        return Err(FaucetError::InvalidNonceAccount.into());
+    }
+
+    if nonce_account.owner == program_id {
+        msg!("Attestation nonce {} was already used", attestation.nonce);
+        return Err(FaucetError::NonceAlreadyUsed.into());
+    }
+
+    create_pda_account(
+        payer_account,
+        nonce_account,
+        system_program,
+        program_id,
+        AttestationNonce::LEN,
+        &[
+            ATTESTATION_NONCE_SEED,
+            faucet_account.key.as_ref(),
+            &attestation.nonce.to_le_bytes(),
+            &[nonce_bump],
+        ],
+    )?;
+
+    AttestationNonce {
+        account_type: AccountType::AttestationNonce,
+    }
+    .serialize(&mut &mut nonce_account.data.borrow_mut()[..])
+    .map_err(|_| FaucetError::InvalidNonceAccount)?;
+
+    Ok(())
+}
+
+// Whether the denylist entry PDA of the wallet holds a live marker
+fn is_denylisted(
+    program_id: &Pubkey,
+    faucet_account: &AccountInfo,
+    wallet_account: &AccountInfo,
+    denylist_entry_account: &AccountInfo,
+) -> Result<bool, ProgramError> {
+    let (denylist_entry_address, _) =
+        find_denylist_entry_address(program_id, faucet_account.key, wallet_account.key);
+    if *denylist_entry_account.key != denylist_entry_address {
+        msg!("Denylist entry account does not match the faucet and wallet");
+        return Err(FaucetError::InvalidDenylistEntry.into());
+    }
+
+    Ok(denylist_entry_account.owner == program_id
+        && DenylistEntry::try_from_slice(&denylist_entry_account.data.borrow())
+            .is_ok_and(|entry| entry.account_type == AccountType::DenylistEntry))
+}
+
+// Checks the proof of the wallet's leaf and marks it claimed, returns the leaf amount
+#[allow(clippy::too_many_arguments)]
+fn claim_merkle_leaf<'a>(
+    program_id: &Pubkey,
+    faucet_account: &AccountInfo<'a>,
+    wallet_account: &AccountInfo<'a>,
+    claim_receipt_account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    merkle_root: &[u8; 32],
+    merkle_claim: Option<MerkleClaim>,
+) -> Result<u64, ProgramError> {
+    let Some(MerkleClaim { amount, proof }) = merkle_claim else {
+        msg!("Faucet has a Merkle root, the claim needs a proof");
+        return Err(FaucetError::InvalidMerkleProof.into());
+    };
+
+    if !merkle::verify(
+        merkle_root,
+        merkle::leaf_hash(wallet_account.key, amount),
+        &proof,
+    ) {
+        msg!(
+            "Proof doesn't match {} claiming {}",
+            wallet_account.key,
+            amount
+        );
+        return Err(FaucetError::InvalidMerkleProof.into());
+    }
+
+    let (claim_receipt_address, claim_receipt_bump) = find_claim_receipt_address(
+        program_id,
+        faucet_account.key,
+        merkle_root,
+        wallet_account.key,
+    );
+    if *claim_receipt_account.key != claim_receipt_address {
+        msg!("Claim receipt account does not match the faucet, root and recipient");
+        return Err(FaucetError::InvalidClaimReceipt.into());
+    }
+
+    if claim_receipt_account.owner == program_id {
+        msg!("{} already claimed its Merkle leaf", wallet_account.key);
+        return Err(FaucetError::AlreadyClaimed.into());
+    }
+
+    create_pda_account(
+        payer_account,
+        claim_receipt_account,
+        system_program,
+        program_id,
+        ClaimReceipt::LEN,
+        &[
+            CLAIM_RECEIPT_SEED,
+            faucet_account.key.as_ref(),
+            merkle_root,
+            wallet_account.key.as_ref(),
+            &[claim_receipt_bump],
+        ],
+    )?;
+
+    ClaimReceipt {
+        account_type: AccountType::ClaimReceipt,
+        amount,
+    }
+    .serialize(&mut &mut claim_receipt_account.data.borrow_mut()[..])
+    .map_err(|_| FaucetError::InvalidClaimReceipt)?;
+
+    Ok(amount)
+}
+
+// The wallet is allowlisted when its entry PDA holds a live marker
+fn check_allowlist_entry(
+    program_id: &Pubkey,
+    faucet_account: &AccountInfo,
+    wallet_account: &AccountInfo,
+    allowlist_entry_account: &AccountInfo,
+) -> ProgramResult {
+    let (allowlist_entry_address, _) =
+        find_allowlist_entry_address(program_id, faucet_account.key, wallet_account.key);
+    if *allowlist_entry_account.key != allowlist_entry_address {
+        msg!("Allowlist entry account does not match the faucet and wallet");
+        return Err(FaucetError::InvalidAllowlistEntry.into());
+    }
+
+    let listed = allowlist_entry_account.owner == program_id
+        && AllowlistEntry::try_from_slice(&allowlist_entry_account.data.borrow())
+            .is_ok_and(|entry| entry.account_type == AccountType::AllowlistEntry);
+    if !listed {
+        msg!("{} is not on the allowlist", wallet_account.key);
+        return Err(FaucetError::NotAllowlisted.into());
+    }
+
+    Ok(())
+}
+
+// Admin instructions need the faucet admin's signature
+fn check_admin(faucet_state: &FaucetState, admin_account: &AccountInfo) -> ProgramResult {
+    if !admin_account.is_signer {
+        msg!("Admin account must be a signer");
+        return Err(FaucetError::MissingSignature.into());
+    }
+
+    if faucet_state.admin != *admin_account.key {
+        msg!("Admin account must be the faucet admin");
+        return Err(FaucetError::NotAdmin.into());
+    }
+
+    Ok(())
+}
+
+// Token faucets only work with the mint and vault recorded at initialization
+fn check_token_accounts(
+    faucet_state: &FaucetState,
+    mint_account: &AccountInfo,
+    vault_account: &AccountInfo,
+) -> ProgramResult {
+    check_faucet_mint(faucet_state, mint_account)?;
+
+    if *vault_account.key != faucet_state.vault {
+        msg!("Vault account must be the faucet vault");
+        return Err(FaucetError::InvalidTokenAccount.into());
+    }
+
+    Ok(())
+}
+
+fn check_faucet_mint(faucet_state: &FaucetState, mint_account: &AccountInfo) -> ProgramResult {
+    if *mint_account.key != faucet_state.mint {
+        msg!("Mint account must be the faucet mint");
+        return Err(FaucetError::InvalidMint.into());
+    }
+
+    Ok(())
+}
+
+// Sends every lamport of a program account to `destination` and wipes its data
+fn close_program_account(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    let destination_lamports = destination
+        .lamports()
+        .checked_add(account.lamports())
+        .ok_or(FaucetError::AmountOverflow)?;
+
+    **account.try_borrow_mut_lamports()? = 0;
+    **destination.try_borrow_mut_lamports()? = destination_lamports;
+
+    // Zeroed data reads as uninitialized, the runtime drops the empty account
+    account.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+// Moves lamports out of the faucet, keeping it rent-exempt
+fn pay_out_lamports(
+    faucet_account: &AccountInfo,
+    destination_account: &AccountInfo,
+    amount: u64,
+) -> ProgramResult {
+    // Only the balance above the rent-exempt reserve can be paid out
+    if amount > available_lamports(faucet_account)? {
+        msg!(
+            "Faucet can't cover {} lamports without dropping below rent exemption",
+            amount
+        );
+        return Err(FaucetError::InsufficientFunds.into());
+    }
+
+    let faucet_lamports = faucet_account
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(FaucetError::InsufficientFunds)?;
+    let destination_lamports = destination_account
+        .lamports()
+        .checked_add(amount)
+        .ok_or(FaucetError::AmountOverflow)?;
+
+    **faucet_account.try_borrow_mut_lamports()? = faucet_lamports;
+    **destination_account.try_borrow_mut_lamports()? = destination_lamports;
+
+    Ok(())
+}
+
+// Lamports an account holds above its rent-exempt reserve
+fn available_lamports(account: &AccountInfo) -> Result<u64, ProgramError> {
+    let reserve = Rent::get()?.minimum_balance(account.data_len());
+    Ok(account.lamports().saturating_sub(reserve))
+}
+
+// Creates a program-owned PDA, also when someone already sent lamports to its address
+fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    new_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let required_lamports = Rent::get()?.minimum_balance(space);
+
+    if new_account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                new_account.key,
+                required_lamports,
+                space as u64,
+                program_id,
+            ),
+            &[payer.clone(), new_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        );
+    }
+
+    // create_account refuses funded addresses, so top up, allocate and assign instead
+    let top_up = required_lamports.saturating_sub(new_account.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, new_account.key, top_up),
+            &[payer.clone(), new_account.clone(), system_program.clone()],
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(new_account.key, space as u64),
+        &[new_account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_account.key, program_id),
+        &[new_account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )
+}
@@ -0,0 +1,85 @@
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+// Leaves and nodes hash under different prefixes so a node can't pass for a leaf
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hash of the leaf letting `wallet` claim `amount`
+pub fn leaf_hash(wallet: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, wallet.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+// Pairs are sorted before hashing, proofs don't need to say which side a sibling is on
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if left <= right {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+/// Whether `proof` links `leaf` to `root`
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, sibling| node_hash(&node, sibling));
+    computed == *root
+}
+
+/// Off-chain Merkle tree over `(wallet, amount)` leaves, used to pick the root set with
+/// `SetMerkleRoot` and to build the proofs claimers send with `RequestTokens`
+#[cfg(not(target_os = "solana"))]
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>, // leaves first, the root alone in the last layer
+}
+
+#[cfg(not(target_os = "solana"))]
+impl MerkleTree {
+    /// Builds the tree, an odd node at the end of a layer moves up unchanged
+    pub fn new(leaves: &[(Pubkey, u64)]) -> Self {
+        let mut layers = vec![leaves
+            .iter()
+            .map(|(wallet, amount)| leaf_hash(wallet, *amount))
+            .collect::<Vec<_>>()];
+
+        while layers.last().is_some_and(|layer| layer.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Self { layers }
+    }
+
+    /// Root of the tree, `None` without leaves
+    pub fn root(&self) -> Option<[u8; 32]> {
+        self.layers.last().and_then(|layer| layer.first()).copied()
+    }
+
+    /// Proof of the leaf at `index`, `None` when out of range
+    pub fn proof(&self, index: usize) -> Option<Vec<[u8; 32]>> {
+        if index >= self.layers.first()?.len() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        let mut position = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+
+        Some(proof)
+    }
+}
@@ -0,0 +1,528 @@
+//! Faucet client over JSON RPC: typed reads of faucet accounts for clients and dashboards, and
+//! the init, request and replenish transactions.
+//!
+//! `FaucetClient` blocks on the RPC node, `nonblocking::FaucetClient` is its async twin for
+//! tokio services. Both share `FaucetClientError` and `FaucetView`.
+
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_client::RpcClient,
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_program::{
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+};
+use solana_sdk::{
+    account::Account,
+    signature::{Signature, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use thiserror::Error;
+
+use crate::error::FaucetError;
+use crate::instruction::{self, ClaimOptions, MissingClaimOption};
+use crate::state::{
+    try_find_faucet_address, AccountType, FaucetMode, FaucetState, MAX_FAUCET_NAME_LEN,
+};
+
+pub mod nonblocking;
+
+/// Errors of the faucet clients
+#[derive(Debug, Error)]
+pub enum FaucetClientError {
+    #[error(transparent)]
+    Rpc(Box<ClientError>), // boxed, `ClientError` alone is over 250 bytes
+    #[error("{0} (faucet error {code})", code = *.0 as u32)]
+    Faucet(FaucetError), // the program rejected the transaction
+    #[error("Account {0} does not exist")]
+    AccountNotFound(Pubkey),
+    #[error("Account {address} is owned by {owner}, not by the faucet program")]
+    IncorrectOwner { address: Pubkey, owner: Pubkey },
+    #[error("Account {0} is not a faucet")]
+    NotAFaucet(Pubkey),
+    #[error("Vault {0} is not a token account")]
+    InvalidVault(Pubkey),
+    #[error("Faucet name {0:?} is longer than {MAX_FAUCET_NAME_LEN} bytes")]
+    InvalidFaucetName(String),
+    #[error("Token faucets need a mint")]
+    MissingMint,
+    #[error("Faucet {0} pays out Merkle leaves, claims need a proof")]
+    MerkleProofRequired(Pubkey),
+    #[error("Faucet {0} requires attested claims, request them through the attester")]
+    AttestationRequired(Pubkey),
+    #[error("Faucet {0} pays out tokens, claims need the token program of its mint")]
+    TokenProgramRequired(Pubkey),
+    #[error("Faucet {0} mints its payouts, there is nothing to replenish")]
+    NothingToReplenish(Pubkey),
+}
+
+impl From<ClientError> for FaucetClientError {
+    fn from(err: ClientError) -> Self {
+        Self::Rpc(Box::new(err))
+    }
+}
+
+impl FaucetClientError {
+    /// Error of the transaction made of `instructions`, typed when the faucet program itself
+    /// rejected it.
+    ///
+    /// Custom codes of other instructions, precompiles and programs the faucet calls share the
+    /// range of `FaucetError` codes, they stay `Rpc`. Telling a failed CPI apart needs the
+    /// preflight logs, errors found only at confirmation are decoded from the index alone.
+    pub fn from_transaction_error(
+        err: ClientError,
+        program_id: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Self {
+        match faucet_error(&err, program_id, instructions) {
+            Some(faucet_error) => Self::Faucet(faucet_error),
+            None => Self::Rpc(Box::new(err)),
+        }
+    }
+}
+
+fn faucet_error(
+    err: &ClientError,
+    program_id: &Pubkey,
+    instructions: &[Instruction],
+) -> Option<FaucetError> {
+    let Some(TransactionError::InstructionError(index, InstructionError::Custom(code))) =
+        err.get_transaction_error()
+    else {
+        return None;
+    };
+    if instructions.get(usize::from(index))?.program_id != *program_id {
+        return None;
+    }
+    if failed_program(err).is_some_and(|failed| failed != *program_id) {
+        return None;
+    }
+
+    FaucetError::from_code(code)
+}
+
+// First program the preflight logs report failed, the innermost one when a CPI failed
+fn failed_program(err: &ClientError) -> Option<Pubkey> {
+    let ClientErrorKind::RpcError(RpcError::RpcResponseError {
+        data: RpcResponseErrorData::SendTransactionPreflightFailure(simulation),
+        ..
+    }) = err.kind()
+    else {
+        return None;
+    };
+
+    simulation.logs.as_ref()?.iter().find_map(|log| {
+        let (program, _) = log.strip_prefix("Program ")?.split_once(" failed: ")?;
+        program.parse().ok()
+    })
+}
+
+/// Settings of a new faucet, see `FaucetClient::initialize`
+#[derive(Clone, Debug)]
+pub struct NewFaucet {
+    pub name: String, // seeds the faucet address along with the admin
+    pub distribution_amount: u64,
+    pub cooldown_slots: u64,
+    pub allow_program_owned_recipients: bool,
+    pub mode: FaucetMode,
+    pub mint: Option<Pubkey>, // needed unless `FaucetMode::Native`
+}
+
+/// Faucet account decoded, with the balances derived from it
+#[derive(Debug)]
+pub struct FaucetView {
+    pub address: Pubkey,
+    pub state: FaucetState,
+    pub lamports: u64, // rent included
+    pub rent_exempt_lamports: u64,
+    pub vault_balance: Option<u64>, // base units, only read for `FaucetMode::TokenVault`
+}
+
+impl FaucetView {
+    /// Decodes the faucet at `address`, its owner has to be the program and its first byte
+    /// `AccountType::Faucet`. The vault balance is left for the caller to fill in.
+    pub fn from_account(
+        program_id: &Pubkey,
+        address: &Pubkey,
+        account: &Account,
+        rent_exempt_lamports: u64,
+    ) -> Result<Self, FaucetClientError> {
+        Ok(Self {
+            address: *address,
+            state: decode_faucet_state(program_id, address, account)?,
+            lamports: account.lamports,
+            rent_exempt_lamports,
+            vault_balance: None,
+        })
+    }
+
+    /// What the faucet can still pay out: lamports above rent, or base units in the vault.
+    ///
+    /// `None` for `FaucetMode::MintAuthority`, which mints every payout.
+    pub fn available(&self) -> Option<u64> {
+        match self.state.mode {
+            FaucetMode::Native => Some(self.lamports.saturating_sub(self.rent_exempt_lamports)),
+            FaucetMode::TokenVault => self.vault_balance,
+            FaucetMode::MintAuthority => None,
+        }
+    }
+
+    /// Claims of `distribution_amount` the available amount still funds, `None` when unbounded.
+    ///
+    /// Merkle leaves pay their own amount and Token-2022 transfer fees come on top, both are
+    /// left out.
+    pub fn claims_remaining(&self) -> Option<u64> {
+        self.available()?
+            .checked_div(self.state.distribution_amount)
+    }
+}
+
+/// Reads and drives the faucets of one deployment of the program
+pub struct FaucetClient {
+    rpc_client: RpcClient,
+    program_id: Pubkey,
+}
+
+impl FaucetClient {
+    pub fn new(rpc_client: RpcClient, program_id: Pubkey) -> Self {
+        Self {
+            rpc_client,
+            program_id,
+        }
+    }
+
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.rpc_client
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    /// State of the faucet at `address`, without the balances
+    pub fn get_faucet_state(&self, address: &Pubkey) -> Result<FaucetState, FaucetClientError> {
+        let account = self.get_account(address)?;
+        decode_faucet_state(&self.program_id, address, &account)
+    }
+
+    /// The faucet at `address` with its balances
+    pub fn get_faucet(&self, address: &Pubkey) -> Result<FaucetView, FaucetClientError> {
+        let account = self.get_account(address)?;
+        let rent_exempt_lamports = self
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(account.data.len())?;
+        let mut faucet =
+            FaucetView::from_account(&self.program_id, address, &account, rent_exempt_lamports)?;
+        self.read_vault_balance(&mut faucet)?;
+
+        Ok(faucet)
+    }
+
+    /// Every faucet of the program, the first byte of its accounts tells faucets apart
+    pub fn get_faucets(&self) -> Result<Vec<FaucetView>, FaucetClientError> {
+        let accounts = self
+            .rpc_client
+            .get_program_accounts_with_config(&self.program_id, faucets_config())?;
+        let rent_exempt_lamports = self
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(FaucetState::LEN)?;
+
+        let mut faucets = decode_faucets(&self.program_id, &accounts, rent_exempt_lamports);
+        for faucet in &mut faucets {
+            self.read_vault_balance(faucet)?;
+        }
+
+        Ok(faucets)
+    }
+
+    /// Creates the faucet `admin` names `new_faucet.name`, the admin pays its rent.
+    ///
+    /// Returns the faucet address with the transaction signature.
+    pub fn initialize(
+        &self,
+        admin: &dyn Signer,
+        new_faucet: &NewFaucet,
+    ) -> Result<(Pubkey, Signature), FaucetClientError> {
+        let token_program = self.token_program_of(initialize_mint(new_faucet))?;
+        let (faucet, instruction) =
+            initialize_instruction(&self.program_id, &admin.pubkey(), new_faucet, token_program)?;
+        let signature = self.send(&[instruction], admin)?;
+
+        Ok((faucet, signature))
+    }
+
+    /// Claims a payout of `faucet` for `recipient`, `payer` signs and pays the fees.
+    ///
+    /// Merkle faucets need `options.merkle_claim`. Attested claims go through the attester and
+    /// are refused here.
+    pub fn request_tokens(
+        &self,
+        faucet: &Pubkey,
+        recipient: &Pubkey,
+        payer: &dyn Signer,
+        options: &ClaimOptions,
+    ) -> Result<Signature, FaucetClientError> {
+        let faucet_state = self.get_faucet_state(faucet)?;
+        let token_program = self.token_program_of(claim_mint(&faucet_state, options))?;
+        let instruction = claim_instruction(
+            &self.program_id,
+            faucet,
+            &faucet_state,
+            recipient,
+            &payer.pubkey(),
+            options,
+            token_program,
+        )?;
+
+        self.send(&[instruction], payer)
+    }
+
+    /// Tops `faucet` up with `amount` lamports or base units of its mint from `admin`
+    pub fn replenish(
+        &self,
+        faucet: &Pubkey,
+        admin: &dyn Signer,
+        amount: u64,
+    ) -> Result<Signature, FaucetClientError> {
+        let faucet_state = self.get_faucet_state(faucet)?;
+        let token_program = self.token_program_of(replenish_mint(faucet, &faucet_state)?)?;
+        let instruction = replenish_instruction(
+            &self.program_id,
+            faucet,
+            &faucet_state,
+            &admin.pubkey(),
+            token_program,
+            amount,
+        )?;
+
+        self.send(&[instruction], admin)
+    }
+
+    /// Sends `instructions` in one transaction `signer` signs and pays the fees of, errors of the
+    /// faucet instructions come out as `FaucetClientError::Faucet`
+    pub fn send(
+        &self,
+        instructions: &[Instruction],
+        signer: &dyn Signer,
+    ) -> Result<Signature, FaucetClientError> {
+        let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            recent_blockhash,
+        );
+
+        self.rpc_client
+            .send_and_confirm_transaction(&transaction)
+            .map_err(|err| {
+                FaucetClientError::from_transaction_error(err, &self.program_id, instructions)
+            })
+    }
+
+    /// Program owning `mint`, token accounts of the mint are derived with it
+    pub fn token_program(&self, mint: &Pubkey) -> Result<Pubkey, FaucetClientError> {
+        Ok(self.get_account(mint)?.owner)
+    }
+
+    fn token_program_of(&self, mint: Option<Pubkey>) -> Result<Option<Pubkey>, FaucetClientError> {
+        mint.map(|mint| self.token_program(&mint)).transpose()
+    }
+
+    fn get_account(&self, address: &Pubkey) -> Result<Account, FaucetClientError> {
+        self.rpc_client
+            .get_account_with_commitment(address, self.rpc_client.commitment())?
+            .value
+            .ok_or(FaucetClientError::AccountNotFound(*address))
+    }
+
+    fn read_vault_balance(&self, faucet: &mut FaucetView) -> Result<(), FaucetClientError> {
+        if faucet.state.mode != FaucetMode::TokenVault {
+            return Ok(());
+        }
+
+        let balance = self
+            .rpc_client
+            .get_token_account_balance(&faucet.state.vault)?;
+        faucet.vault_balance = Some(parse_vault_balance(&faucet.state.vault, &balance.amount)?);
+        Ok(())
+    }
+}
+
+fn decode_faucet_state(
+    program_id: &Pubkey,
+    address: &Pubkey,
+    account: &Account,
+) -> Result<FaucetState, FaucetClientError> {
+    if account.owner != *program_id {
+        return Err(FaucetClientError::IncorrectOwner {
+            address: *address,
+            owner: account.owner,
+        });
+    }
+
+    FaucetState::unpack(&account.data)
+        .ok()
+        .filter(FaucetState::is_initialized)
+        .ok_or(FaucetClientError::NotAFaucet(*address))
+}
+
+// Program accounts whose first byte is `AccountType::Faucet`
+fn faucets_config() -> RpcProgramAccountsConfig {
+    RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            vec![AccountType::Faucet as u8],
+        ))]),
+        ..RpcProgramAccountsConfig::default()
+    }
+}
+
+// Skips the rare account whose first byte matches `faucets_config` by chance
+fn decode_faucets(
+    program_id: &Pubkey,
+    accounts: &[(Pubkey, Account)],
+    rent_exempt_lamports: u64,
+) -> Vec<FaucetView> {
+    accounts
+        .iter()
+        .filter_map(|(address, account)| {
+            FaucetView::from_account(program_id, address, account, rent_exempt_lamports).ok()
+        })
+        .collect()
+}
+
+// Token balances come back as decimal strings of base units
+fn parse_vault_balance(vault: &Pubkey, amount: &str) -> Result<u64, FaucetClientError> {
+    amount
+        .parse()
+        .map_err(|_| FaucetClientError::InvalidVault(*vault))
+}
+
+// The mints below are the ones whose owner the clients read for the token program
+
+fn initialize_mint(new_faucet: &NewFaucet) -> Option<Pubkey> {
+    new_faucet
+        .mint
+        .filter(|_| new_faucet.mode != FaucetMode::Native)
+}
+
+fn claim_mint(faucet_state: &FaucetState, options: &ClaimOptions) -> Option<Pubkey> {
+    (options.token_program.is_none() && faucet_state.mode != FaucetMode::Native)
+        .then_some(faucet_state.mint)
+}
+
+// Refuses minting faucets before anything is read
+fn replenish_mint(
+    faucet: &Pubkey,
+    faucet_state: &FaucetState,
+) -> Result<Option<Pubkey>, FaucetClientError> {
+    match faucet_state.mode {
+        FaucetMode::Native => Ok(None),
+        FaucetMode::TokenVault => Ok(Some(faucet_state.mint)),
+        FaucetMode::MintAuthority => Err(FaucetClientError::NothingToReplenish(*faucet)),
+    }
+}
+
+fn initialize_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    new_faucet: &NewFaucet,
+    token_program: Option<Pubkey>,
+) -> Result<(Pubkey, Instruction), FaucetClientError> {
+    let (faucet, _) = try_find_faucet_address(program_id, admin, &new_faucet.name)
+        .ok_or_else(|| FaucetClientError::InvalidFaucetName(new_faucet.name.clone()))?;
+    let instruction = match (new_faucet.mode, new_faucet.mint.zip(token_program)) {
+        (FaucetMode::Native, _) => instruction::initialize(
+            program_id,
+            &faucet,
+            admin,
+            &new_faucet.name,
+            new_faucet.distribution_amount,
+            new_faucet.cooldown_slots,
+            new_faucet.allow_program_owned_recipients,
+        ),
+        (FaucetMode::TokenVault, Some((mint, token_program))) => {
+            instruction::initialize_token_vault(
+                program_id,
+                &faucet,
+                admin,
+                &new_faucet.name,
+                new_faucet.distribution_amount,
+                new_faucet.cooldown_slots,
+                new_faucet.allow_program_owned_recipients,
+                &mint,
+                &token_program,
+            )
+        }
+        (FaucetMode::MintAuthority, Some((mint, token_program))) => {
+            instruction::initialize_mint_authority(
+                program_id,
+                &faucet,
+                admin,
+                &new_faucet.name,
+                new_faucet.distribution_amount,
+                new_faucet.cooldown_slots,
+                new_faucet.allow_program_owned_recipients,
+                &mint,
+                &token_program,
+            )
+        }
+        (_, None) => return Err(FaucetClientError::MissingMint),
+    };
+
+    Ok((faucet, instruction))
+}
+
+// Refuses the claims the program would reject for lack of a proof or an attestation, attested
+// claims even with a nonce
+fn claim_instruction(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    faucet_state: &FaucetState,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+    options: &ClaimOptions,
+    token_program: Option<Pubkey>,
+) -> Result<Instruction, FaucetClientError> {
+    if faucet_state.attester.is_some() {
+        return Err(FaucetClientError::AttestationRequired(*faucet));
+    }
+
+    let options = ClaimOptions {
+        token_program: options.token_program.or(token_program),
+        ..options.clone()
+    };
+    instruction::request_tokens_for(program_id, faucet, faucet_state, recipient, payer, &options)
+        .map_err(|missing| match missing {
+            MissingClaimOption::MerkleClaim => FaucetClientError::MerkleProofRequired(*faucet),
+            MissingClaimOption::AttestationNonce => FaucetClientError::AttestationRequired(*faucet),
+            MissingClaimOption::TokenProgram => FaucetClientError::TokenProgramRequired(*faucet),
+        })
+}
+
+fn replenish_instruction(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    faucet_state: &FaucetState,
+    admin: &Pubkey,
+    token_program: Option<Pubkey>,
+    amount: u64,
+) -> Result<Instruction, FaucetClientError> {
+    match (faucet_state.mode, token_program) {
+        (FaucetMode::Native, _) => Ok(instruction::replenish(program_id, faucet, admin, amount)),
+        (FaucetMode::TokenVault, Some(token_program)) => Ok(instruction::replenish_token_vault(
+            program_id,
+            faucet,
+            admin,
+            &faucet_state.mint,
+            &token_program,
+            amount,
+        )),
+        (FaucetMode::TokenVault, None) => Err(FaucetClientError::MissingMint),
+        (FaucetMode::MintAuthority, _) => Err(FaucetClientError::NothingToReplenish(*faucet)),
+    }
+}
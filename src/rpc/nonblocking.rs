@@ -0,0 +1,208 @@
+//! Async faucet client on the nonblocking RPC client, for tokio services
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::{
+    account::Account,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+
+use super::{
+    claim_instruction, claim_mint, decode_faucet_state, decode_faucets, faucets_config,
+    initialize_instruction, initialize_mint, parse_vault_balance, replenish_instruction,
+    replenish_mint, FaucetClientError, FaucetView, NewFaucet,
+};
+use crate::instruction::ClaimOptions;
+use crate::state::{FaucetMode, FaucetState};
+
+/// Reads and drives the faucets of one deployment of the program, see `rpc::FaucetClient`
+pub struct FaucetClient {
+    rpc_client: RpcClient,
+    program_id: Pubkey,
+}
+
+impl FaucetClient {
+    pub fn new(rpc_client: RpcClient, program_id: Pubkey) -> Self {
+        Self {
+            rpc_client,
+            program_id,
+        }
+    }
+
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.rpc_client
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    /// State of the faucet at `address`, without the balances
+    pub async fn get_faucet_state(
+        &self,
+        address: &Pubkey,
+    ) -> Result<FaucetState, FaucetClientError> {
+        let account = self.get_account(address).await?;
+        decode_faucet_state(&self.program_id, address, &account)
+    }
+
+    /// The faucet at `address` with its balances
+    pub async fn get_faucet(&self, address: &Pubkey) -> Result<FaucetView, FaucetClientError> {
+        let account = self.get_account(address).await?;
+        let rent_exempt_lamports = self
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(account.data.len())
+            .await?;
+        let mut faucet =
+            FaucetView::from_account(&self.program_id, address, &account, rent_exempt_lamports)?;
+        self.read_vault_balance(&mut faucet).await?;
+
+        Ok(faucet)
+    }
+
+    /// Every faucet of the program, the first byte of its accounts tells faucets apart
+    pub async fn get_faucets(&self) -> Result<Vec<FaucetView>, FaucetClientError> {
+        let accounts = self
+            .rpc_client
+            .get_program_accounts_with_config(&self.program_id, faucets_config())
+            .await?;
+        let rent_exempt_lamports = self
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(FaucetState::LEN)
+            .await?;
+
+        let mut faucets = decode_faucets(&self.program_id, &accounts, rent_exempt_lamports);
+        for faucet in &mut faucets {
+            self.read_vault_balance(faucet).await?;
+        }
+
+        Ok(faucets)
+    }
+
+    /// Creates the faucet `admin` names `new_faucet.name`, the admin pays its rent.
+    ///
+    /// Returns the faucet address with the transaction signature.
+    pub async fn initialize(
+        &self,
+        admin: &(dyn Signer + Sync),
+        new_faucet: &NewFaucet,
+    ) -> Result<(Pubkey, Signature), FaucetClientError> {
+        let token_program = self.token_program_of(initialize_mint(new_faucet)).await?;
+        let (faucet, instruction) =
+            initialize_instruction(&self.program_id, &admin.pubkey(), new_faucet, token_program)?;
+        let signature = self.send(&[instruction], admin).await?;
+
+        Ok((faucet, signature))
+    }
+
+    /// Claims a payout of `faucet` for `recipient`, `payer` signs and pays the fees.
+    ///
+    /// Merkle faucets need `options.merkle_claim`. Attested claims go through the attester and
+    /// are refused here.
+    pub async fn request_tokens(
+        &self,
+        faucet: &Pubkey,
+        recipient: &Pubkey,
+        payer: &(dyn Signer + Sync),
+        options: &ClaimOptions,
+    ) -> Result<Signature, FaucetClientError> {
+        let faucet_state = self.get_faucet_state(faucet).await?;
+        let token_program = self
+            .token_program_of(claim_mint(&faucet_state, options))
+            .await?;
+        let instruction = claim_instruction(
+            &self.program_id,
+            faucet,
+            &faucet_state,
+            recipient,
+            &payer.pubkey(),
+            options,
+            token_program,
+        )?;
+
+        self.send(&[instruction], payer).await
+    }
+
+    /// Tops `faucet` up with `amount` lamports or base units of its mint from `admin`
+    pub async fn replenish(
+        &self,
+        faucet: &Pubkey,
+        admin: &(dyn Signer + Sync),
+        amount: u64,
+    ) -> Result<Signature, FaucetClientError> {
+        let faucet_state = self.get_faucet_state(faucet).await?;
+        let token_program = self
+            .token_program_of(replenish_mint(faucet, &faucet_state)?)
+            .await?;
+        let instruction = replenish_instruction(
+            &self.program_id,
+            faucet,
+            &faucet_state,
+            &admin.pubkey(),
+            token_program,
+            amount,
+        )?;
+
+        self.send(&[instruction], admin).await
+    }
+
+    /// Sends `instructions` in one transaction `signer` signs and pays the fees of, see
+    /// `rpc::FaucetClient::send`
+    pub async fn send(
+        &self,
+        instructions: &[Instruction],
+        signer: &(dyn Signer + Sync),
+    ) -> Result<Signature, FaucetClientError> {
+        let recent_blockhash = self.rpc_client.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            recent_blockhash,
+        );
+
+        self.rpc_client
+            .send_and_confirm_transaction(&transaction)
+            .await
+            .map_err(|err| {
+                FaucetClientError::from_transaction_error(err, &self.program_id, instructions)
+            })
+    }
+
+    /// Program owning `mint`, token accounts of the mint are derived with it
+    pub async fn token_program(&self, mint: &Pubkey) -> Result<Pubkey, FaucetClientError> {
+        Ok(self.get_account(mint).await?.owner)
+    }
+
+    async fn token_program_of(
+        &self,
+        mint: Option<Pubkey>,
+    ) -> Result<Option<Pubkey>, FaucetClientError> {
+        match mint {
+            Some(mint) => Ok(Some(self.token_program(&mint).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_account(&self, address: &Pubkey) -> Result<Account, FaucetClientError> {
+        self.rpc_client
+            .get_account_with_commitment(address, self.rpc_client.commitment())
+            .await?
+            .value
+            .ok_or(FaucetClientError::AccountNotFound(*address))
+    }
+
+    async fn read_vault_balance(&self, faucet: &mut FaucetView) -> Result<(), FaucetClientError> {
+        if faucet.state.mode != FaucetMode::TokenVault {
+            return Ok(());
+        }
+
+        let balance = self
+            .rpc_client
+            .get_token_account_balance(&faucet.state.vault)
+            .await?;
+        faucet.vault_balance = Some(parse_vault_balance(&faucet.state.vault, &balance.amount)?);
+        Ok(())
+    }
+}
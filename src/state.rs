@@ -1,8 +1,293 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::pubkey::Pubkey;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::error::FaucetError;
+
+/// Seed prefix of the faucet PDA
+pub const FAUCET_SEED: &[u8] = b"faucet";
+
+/// Longest faucet name, names are PDA seeds and a seed is at most 32 bytes
+pub const MAX_FAUCET_NAME_LEN: usize = 32;
+
+/// Seed prefix of the per-recipient claim record PDA
+pub const CLAIM_RECORD_SEED: &[u8] = b"claim";
+
+/// Seed prefix of the per-wallet allowlist entry PDA
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+
+/// Seed prefix of the per-wallet Merkle claim receipt PDA
+pub const CLAIM_RECEIPT_SEED: &[u8] = b"receipt";
+
+/// Seed prefix of the per-wallet denylist entry PDA
+pub const DENYLIST_SEED: &[u8] = b"denylist";
+
+/// Seed prefix of the PDA marking an attestation nonce as used
+pub const ATTESTATION_NONCE_SEED: &[u8] = b"nonce";
+
+/// First byte of every account owned by the program.
+///
+/// Zeroed data decodes as `Uninitialized`, new variants go at the end.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AccountType {
+    #[default]
+    Uninitialized,
+    Faucet,
+    ClaimRecord,
+    AllowlistEntry,
+    ClaimReceipt,
+    DenylistEntry,
+    AttestationNonce,
+}
+
+/// What the faucet pays out
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FaucetMode {
+    /// Lamports held by the faucet account
+    #[default]
+    Native,
+    /// SPL tokens held by the faucet's token vault
+    TokenVault,
+    /// SPL tokens minted on demand, the faucet PDA is the mint authority
+    MintAuthority,
+}
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct FaucetState {
+    pub account_type: AccountType,
+    pub bump: u8,
+    pub creator: Pubkey, // admin at initialization, part of the PDA seeds
     pub admin: Pubkey,
     pub distribution_amount: u64,
+    pub cooldown_slots: u64, // slots a recipient has to wait between two claims
+    pub allow_program_owned_recipients: bool,
+    pub paused: bool,                  // payouts are stopped while set
+    pub pending_admin: Option<Pubkey>, // proposed admin, takes over once it accepts
+    pub mode: FaucetMode,
+    pub mint: Pubkey,      // default for native faucets
+    pub vault: Pubkey, // associated token account of the faucet PDA, default unless `TokenVault`
+    pub name: String,  // tells apart the faucets of one creator, part of the PDA seeds
+    pub window_slots: u64, // length of the distribution window, 0 disables the window cap
+    pub window_cap: u64, // most the faucet pays out within one window
+    pub window_start_slot: u64,
+    pub window_distributed: u64,     // paid out since `window_start_slot`
+    pub recipient_claim_limit: u64,  // lifetime claims of one recipient, 0 for no limit
+    pub recipient_amount_limit: u64, // lifetime amount of one recipient, 0 for no limit
+    pub allowlist_enabled: bool,     // only wallets with an allowlist entry can claim
+    pub merkle_root: Option<[u8; 32]>, // only wallets with a leaf can claim, each its leaf amount
+    pub denylist_len: u64,           // live denylist entries, claims skip the check at zero
+    pub attester: Option<Pubkey>,    // claims need an attestation signed by it when set
+}
+
+impl FaucetState {
+    pub const LEN: usize = 1
+        + 1
+        + 32
+        + 32
+        + 8
+        + 8
+        + 1
+        + 1
+        + (1 + 32)
+        + 1
+        + 32
+        + 32
+        + (4 + MAX_FAUCET_NAME_LEN)
+        + 8 * 4
+        + 8
+        + 8
+        + 1
+        + (1 + 32)
+        + 8
+        + (1 + 32);
+
+    pub fn is_initialized(&self) -> bool {
+        self.account_type == AccountType::Faucet
+    }
+
+    /// Decodes the state from account data.
+    ///
+    /// `None` options encode shorter than `LEN`, so the unused tail is ignored.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| FaucetError::InvalidFaucetData.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        self.serialize(&mut &mut data[..])
+            .map_err(|_| FaucetError::InvalidFaucetData.into())
+    }
+
+    /// Seeds for signing as the faucet PDA
+    pub fn signer_seeds(&self) -> [&[u8]; 4] {
+        [
+            FAUCET_SEED,
+            self.creator.as_ref(),
+            self.name.as_bytes(),
+            std::slice::from_ref(&self.bump),
+        ]
+    }
+}
+
+/// Tracks the claims of one recipient from one faucet
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct ClaimRecord {
+    pub account_type: AccountType,
+    pub last_claim_slot: u64,
+    pub claim_count: u64,
+    pub total_claimed: u64,
+    pub extra_claims: u64, // granted by the admin on top of the faucet limits
+    pub extra_amount: u64,
+}
+
+impl ClaimRecord {
+    pub const LEN: usize = 1 + 8 + 8 + 8 + 8 + 8;
+
+    /// Whether one more claim of `amount` stays within the faucet limits
+    pub fn can_claim(&self, faucet_state: &FaucetState, amount: u64) -> bool {
+        let within_claims = faucet_state.recipient_claim_limit == 0
+            || self.claim_count
+                < faucet_state
+                    .recipient_claim_limit
+                    .saturating_add(self.extra_claims);
+        let within_amount = faucet_state.recipient_amount_limit == 0
+            || self.total_claimed.saturating_add(amount)
+                <= faucet_state
+                    .recipient_amount_limit
+                    .saturating_add(self.extra_amount);
+
+        within_claims && within_amount
+    }
+}
+
+/// Marks a wallet as allowed to claim from one faucet, closing it removes the wallet
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AllowlistEntry {
+    pub account_type: AccountType,
+}
+
+impl AllowlistEntry {
+    pub const LEN: usize = 1;
+}
+
+/// Marks the Merkle leaf of a wallet as claimed under one root
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ClaimReceipt {
+    pub account_type: AccountType,
+    pub amount: u64,
+}
+
+impl ClaimReceipt {
+    pub const LEN: usize = 1 + 8;
+}
+
+/// Blocks a wallet from claiming from one faucet, closing it lifts the block
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DenylistEntry {
+    pub account_type: AccountType,
+}
+
+impl DenylistEntry {
+    pub const LEN: usize = 1;
+}
+
+/// Marks an attestation nonce as used, its claim can't be replayed
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AttestationNonce {
+    pub account_type: AccountType,
+}
+
+impl AttestationNonce {
+    pub const LEN: usize = 1;
+}
+
+/// Derives the PDA of the faucet `name` created by `Initialize` for `creator`.
+///
+/// Panics when `name` is longer than `MAX_FAUCET_NAME_LEN` bytes, see `try_find_faucet_address`.
+pub fn find_faucet_address(program_id: &Pubkey, creator: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[FAUCET_SEED, creator.as_ref(), name.as_bytes()],
+        program_id,
+    )
+}
+
+/// `find_faucet_address` for names taken from users, `None` when `name` is longer than
+/// `MAX_FAUCET_NAME_LEN` bytes
+pub fn try_find_faucet_address(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    name: &str,
+) -> Option<(Pubkey, u8)> {
+    if name.len() > MAX_FAUCET_NAME_LEN {
+        return None;
+    }
+
+    Pubkey::try_find_program_address(
+        &[FAUCET_SEED, creator.as_ref(), name.as_bytes()],
+        program_id,
+    )
+}
+
+pub fn find_claim_record_address(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    recipient: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[CLAIM_RECORD_SEED, faucet.as_ref(), recipient.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_allowlist_entry_address(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    wallet: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ALLOWLIST_SEED, faucet.as_ref(), wallet.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_denylist_entry_address(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    wallet: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[DENYLIST_SEED, faucet.as_ref(), wallet.as_ref()],
+        program_id,
+    )
+}
+
+/// Receipts are per root, a new root starts a fresh list
+pub fn find_claim_receipt_address(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    merkle_root: &[u8; 32],
+    wallet: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            CLAIM_RECEIPT_SEED,
+            faucet.as_ref(),
+            merkle_root,
+            wallet.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+pub fn find_attestation_nonce_address(
+    program_id: &Pubkey,
+    faucet: &Pubkey,
+    nonce: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            ATTESTATION_NONCE_SEED,
+            faucet.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
 }
@@ -0,0 +1,341 @@
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_option::COption,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::{
+    extension::{
+        memo_transfer::memo_required, transfer_fee::TransferFeeConfig, BaseStateWithExtensions,
+        StateWithExtensions,
+    },
+    state::{Account as TokenAccount, Mint},
+};
+
+use crate::error::FaucetError;
+
+// Legacy SPL Token and Token-2022 are supported, the mint owner picks which one
+pub(crate) fn check_token_program(token_program: &AccountInfo) -> ProgramResult {
+    if *token_program.key != spl_token::id() && *token_program.key != spl_token_2022::id() {
+        msg!("Token program must be the SPL Token or Token-2022 program");
+        return Err(FaucetError::InvalidTokenProgram.into());
+    }
+
+    Ok(())
+}
+
+pub(crate) fn check_associated_token_program(ata_program: &AccountInfo) -> ProgramResult {
+    if *ata_program.key != spl_associated_token_account::id() {
+        msg!("Associated token program must be the SPL Associated Token Account program");
+        return Err(FaucetError::InvalidTokenProgram.into());
+    }
+
+    Ok(())
+}
+
+// Decodes a mint owned by `token_program`, ignoring its extensions
+pub(crate) fn load_mint(
+    mint: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<Mint, ProgramError> {
+    if mint.owner != token_program.key {
+        msg!("Mint must be owned by the token program");
+        return Err(FaucetError::InvalidMint.into());
+    }
+
+    let data = mint.data.borrow();
+    let mint_state =
+        StateWithExtensions::<Mint>::unpack(&data).map_err(|_| FaucetError::InvalidMint)?;
+    Ok(mint_state.base)
+}
+
+// Decimals are needed for checked transfers
+pub(crate) fn mint_decimals(
+    mint: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    Ok(load_mint(mint, token_program)?.decimals)
+}
+
+// Amount to send for the recipient to get `net_amount` and the transfer fee it carries,
+// the fee is `None` for mints without a transfer fee
+pub(crate) fn gross_transfer_amount(
+    mint: &AccountInfo,
+    net_amount: u64,
+) -> Result<(u64, Option<u64>), ProgramError> {
+    let data = mint.data.borrow();
+    let mint_state =
+        StateWithExtensions::<Mint>::unpack(&data).map_err(|_| FaucetError::InvalidMint)?;
+    let Ok(fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok((net_amount, None));
+    };
+
+    let transfer_fee = fee_config.get_epoch_fee(Clock::get()?.epoch);
+    let gross_amount = transfer_fee
+        .calculate_pre_fee_amount(net_amount)
+        .ok_or(FaucetError::AmountOverflow)?;
+    let fee = transfer_fee
+        .calculate_fee(gross_amount)
+        .ok_or(FaucetError::AmountOverflow)?;
+
+    Ok((gross_amount, Some(fee)))
+}
+
+// Fee the token program charges on a transfer of `gross_amount`, `None` for mints without one
+pub(crate) fn transfer_fee(
+    mint: &AccountInfo,
+    gross_amount: u64,
+) -> Result<Option<u64>, ProgramError> {
+    let data = mint.data.borrow();
+    let mint_state =
+        StateWithExtensions::<Mint>::unpack(&data).map_err(|_| FaucetError::InvalidMint)?;
+    let Ok(fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(None);
+    };
+
+    let fee = fee_config
+        .get_epoch_fee(Clock::get()?.epoch)
+        .calculate_fee(gross_amount)
+        .ok_or(FaucetError::AmountOverflow)?;
+    Ok(Some(fee))
+}
+
+pub(crate) fn token_balance(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    let data = token_account.data.borrow();
+    let account_state = StateWithExtensions::<TokenAccount>::unpack(&data)
+        .map_err(|_| FaucetError::InvalidTokenAccount)?;
+    Ok(account_state.base.amount)
+}
+
+// Token-2022 accounts can refuse incoming transfers without a memo
+pub(crate) fn requires_memo(token_account: &AccountInfo) -> Result<bool, ProgramError> {
+    if token_account.data_is_empty() {
+        return Ok(false);
+    }
+
+    let data = token_account.data.borrow();
+    let account_state = StateWithExtensions::<TokenAccount>::unpack(&data)
+        .map_err(|_| FaucetError::InvalidTokenAccount)?;
+    Ok(memo_required(&account_state))
+}
+
+// Logs a memo right before a transfer into an account that requires one
+pub(crate) fn memo(memo_program: Option<&AccountInfo>, text: &str) -> ProgramResult {
+    let memo_program = match memo_program {
+        Some(memo_program) if *memo_program.key == spl_memo::id() => memo_program,
+        _ => {
+            msg!("Recipient token account requires a memo, pass the SPL Memo program");
+            return Err(FaucetError::InvalidMemoProgram.into());
+        }
+    };
+
+    invoke(
+        &spl_memo::build_memo(text.as_bytes(), &[]),
+        std::slice::from_ref(memo_program),
+    )
+}
+
+// The associated token account of `wallet` for `mint`
+pub(crate) fn check_associated_token_account(
+    token_account: &AccountInfo,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> ProgramResult {
+    if *token_account.key
+        != get_associated_token_address_with_program_id(wallet, mint, token_program)
+    {
+        msg!(
+            "Token account must be the associated token account of {}",
+            wallet
+        );
+        return Err(FaucetError::InvalidTokenAccount.into());
+    }
+
+    Ok(())
+}
+
+// Creates the associated token account of `wallet` unless it already exists
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_associated_token_account<'a>(
+    payer: &AccountInfo<'a>,
+    token_account: &AccountInfo<'a>,
+    wallet: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    ata_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    invoke(
+        &create_associated_token_account_idempotent(
+            payer.key,
+            wallet.key,
+            mint.key,
+            token_program.key,
+        ),
+        &[
+            payer.clone(),
+            token_account.clone(),
+            wallet.clone(),
+            mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+            ata_program.clone(),
+        ],
+    )
+}
+
+// Moves tokens with `transfer_checked`, `signer_seeds` is empty for wallet authorities.
+//
+// `fee` is the transfer fee the token program has to charge, `None` for mints without one.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn transfer<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    decimals: u8,
+    fee: Option<u64>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instruction = match fee {
+        Some(fee) => {
+            spl_token_2022::extension::transfer_fee::instruction::transfer_checked_with_fee(
+                token_program.key,
+                source.key,
+                mint.key,
+                destination.key,
+                authority.key,
+                &[],
+                amount,
+                decimals,
+                fee,
+            )?
+        }
+        None => spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            source.key,
+            mint.key,
+            destination.key,
+            authority.key,
+            &[],
+            amount,
+            decimals,
+        )?,
+    };
+
+    invoke_signed(
+        &instruction,
+        &[
+            source.clone(),
+            mint.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+// Mints fresh tokens, `authority` signs through `signer_seeds`
+pub(crate) fn mint_to<'a>(
+    token_program: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    invoke_signed(
+        &spl_token_2022::instruction::mint_to_checked(
+            token_program.key,
+            mint.key,
+            destination.key,
+            authority.key,
+            &[],
+            amount,
+            decimals,
+        )?,
+        &[
+            mint.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+// Closes an empty token account, its rent goes to `destination`
+pub(crate) fn close_account<'a>(
+    token_program: &AccountInfo<'a>,
+    token_account: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    invoke_signed(
+        &spl_token_2022::instruction::close_account(
+            token_program.key,
+            token_account.key,
+            destination.key,
+            authority.key,
+            &[],
+        )?,
+        &[
+            token_account.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+// Hands the mint authority of `mint` from `authority` to `new_authority`
+pub(crate) fn set_mint_authority<'a>(
+    token_program: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    new_authority: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    invoke_signed(
+        &spl_token_2022::instruction::set_authority(
+            token_program.key,
+            mint.key,
+            Some(new_authority),
+            spl_token_2022::instruction::AuthorityType::MintTokens,
+            authority.key,
+            &[],
+        )?,
+        &[mint.clone(), authority.clone(), token_program.clone()],
+        signer_seeds,
+    )
+}
+
+pub(crate) fn is_mint_authority(mint: &Mint, authority: &Pubkey) -> bool {
+    mint.mint_authority == COption::Some(*authority)
+}
+
+// The mint authority has to be the faucet for it to mint on demand
+pub(crate) fn check_mint_authority(mint: &Mint, authority: &Pubkey) -> ProgramResult {
+    if !is_mint_authority(mint, authority) {
+        msg!("Mint authority must be {}", authority);
+        return Err(FaucetError::InvalidMintAuthority.into());
+    }
+
+    Ok(())
+}
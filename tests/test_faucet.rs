@@ -1,20 +1,32 @@
 use borsh::BorshDeserialize;
-use simple_token_faucet::instructions::FaucetInstruction;
+use simple_token_faucet::attestation::{new_ed25519_instruction, Attestation};
+use simple_token_faucet::error::FaucetError;
+use simple_token_faucet::instruction::{
+    self, ClaimOptions, ConfigUpdate, MerkleClaim, MissingClaimOption,
+};
+use simple_token_faucet::merkle::MerkleTree;
 use simple_token_faucet::process_instruction;
-use simple_token_faucet::state::FaucetState;
+use simple_token_faucet::state::{
+    find_allowlist_entry_address, find_claim_receipt_address, find_claim_record_address,
+    find_denylist_entry_address, find_faucet_address, AccountType, ClaimReceipt, ClaimRecord,
+    FaucetState, MAX_FAUCET_NAME_LEN,
+};
 use solana_program::rent::Rent;
 use solana_program::{
-    instruction::{AccountMeta, Instruction},
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_error::ProgramError,
     pubkey::Pubkey,
-    system_program, sysvar,
+    system_program,
 };
 use solana_program_test::*;
 use solana_sdk::{
     account::Account,
     signature::{Keypair, Signer},
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
 };
 
+const FAUCET_NAME: &str = "test";
+
 #[tokio::test]
 async fn test_initialize() {
     // Create program and test environment
@@ -26,23 +38,14 @@ async fn test_initialize() {
     );
 
     // Generate keypairs for accounts
-    let faucet_keypair = Keypair::new();
     let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
 
     // Calculate rent-exempt balance
     let rent = Rent::default();
-    let faucet_account_rent = rent.minimum_balance(size_of::<FaucetState>()); // rent exempt
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN); // rent exempt
 
-    // Add faucet account to test environment
-    program_test.add_account(
-        faucet_keypair.pubkey(),
-        Account {
-            lamports: faucet_account_rent,
-            data: vec![0; size_of::<FaucetState>()],
-            owner: program_id,
-            ..Account::default() // use default values for other fields
-        },
-    );
+    // The faucet account doesn't exist yet, Initialize creates it
 
     // Add admin account to test environment
     program_test.add_account(
@@ -58,16 +61,14 @@ async fn test_initialize() {
 
     // Create init instruction
     let distribution_amount = 1000;
-    let init_instruction = Instruction::new_with_borsh(
-        program_id,
-        &FaucetInstruction::Initialize {
-            distribution_amount,
-        },
-        vec![ // defines accounts involved with this instruction
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(admin_keypair.pubkey(), true), // a signer
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-        ],
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
     );
 
     // Create and sign transaction
@@ -78,21 +79,27 @@ async fn test_initialize() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Fetch the faucet account
-    let faucet_account = banks_client
-        .get_account(faucet_keypair.pubkey())
-        .await
-        .unwrap()
-        .unwrap();
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
 
     // Deserialize the faucet account
-    let faucet_state = FaucetState::try_from_slice(&faucet_account.data).unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
 
     // Verify the faucet state
+    assert!(faucet_state.is_initialized());
     assert_eq!(faucet_state.admin, admin_keypair.pubkey());
     assert_eq!(faucet_state.distribution_amount, distribution_amount);
 
-    // Verify the account is still rent-exempt
+    // Verify the program owns the faucet and the admin paid its rent
+    assert_eq!(faucet_account.owner, program_id);
+    assert_eq!(faucet_account.lamports, faucet_account_rent);
     assert!(rent.is_exempt(faucet_account.lamports, faucet_account.data.len()));
+
+    let admin_account = banks_client
+        .get_account(admin_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(admin_account.lamports, 100_000_000 - faucet_account_rent);
 }
 
 #[tokio::test]
@@ -104,32 +111,33 @@ async fn test_request_token() {
         processor!(process_instruction),
     );
 
-    let faucet_keypair = Keypair::new();
     let user_keypair = Keypair::new();
     let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
 
     // Initialize faucet state
     let distribution_amount = 1000;
 
     let rent = Rent::default();
-    let account_size = size_of::<FaucetState>();
+    let account_size = FaucetState::LEN;
     let faucet_account_rent = rent.minimum_balance(account_size);
 
+    // Lamports sent to the faucet address before Initialize stay in the faucet
     program_test.add_account(
-        faucet_keypair.pubkey(),
+        faucet,
         Account {
             lamports: faucet_account_rent + 10_000_000, // Rent + initial balance
-            data: vec![0; account_size],
-            owner: program_id,
+            owner: system_program::id(),
             ..Account::default()
         },
     );
 
-    // Add user account
+    // Add user account, funded to pay for its claim record
+    let user_lamports = 10_000_000;
     program_test.add_account(
         user_keypair.pubkey(),
         Account {
-            lamports: rent.minimum_balance(0),
+            lamports: user_lamports,
             owner: system_program::id(),
             ..Account::default()
         },
@@ -148,16 +156,14 @@ async fn test_request_token() {
     let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
 
     // Initialize the faucet
-    let init_instruction = Instruction::new_with_borsh(
-        program_id,
-        &FaucetInstruction::Initialize {
-            distribution_amount,
-        },
-        vec![
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(admin_keypair.pubkey(), true),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-        ],
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
     );
 
     let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
@@ -166,14 +172,11 @@ async fn test_request_token() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Request tokens
-    let request_instruction = Instruction::new_with_borsh(
-        program_id,
-        &FaucetInstruction::RequestTokens,
-        vec![
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(user_keypair.pubkey(), true),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
+    let request_instruction = instruction::request_tokens(
+        &program_id,
+        &faucet,
+        &user_keypair.pubkey(),
+        &user_keypair.pubkey(),
     );
 
     let mut transaction =
@@ -184,11 +187,7 @@ async fn test_request_token() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Check balances
-    let faucet_account = banks_client
-        .get_account(faucet_keypair.pubkey())
-        .await
-        .unwrap()
-        .unwrap();
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
     let user_account = banks_client
         .get_account(user_keypair.pubkey())
         .await
@@ -199,9 +198,10 @@ async fn test_request_token() {
         faucet_account.lamports,
         faucet_account_rent + 10_000_000 - distribution_amount
     );
+    // The user paid the rent of its claim record
     assert_eq!(
         user_account.lamports,
-        rent.minimum_balance(0) + distribution_amount
+        user_lamports + distribution_amount - rent.minimum_balance(ClaimRecord::LEN)
     );
 }
 
@@ -214,22 +214,21 @@ async fn test_replenish_token() {
         processor!(process_instruction),
     );
 
-    let faucet_keypair = Keypair::new();
     let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
 
     // Initialize faucet state
     let distribution_amount = 1000;
 
     let rent = Rent::default();
-    let account_size = size_of::<FaucetState>();
+    let account_size = FaucetState::LEN;
     let faucet_account_rent = rent.minimum_balance(account_size);
 
     program_test.add_account(
-        faucet_keypair.pubkey(),
+        faucet,
         Account {
             lamports: faucet_account_rent + 10_000_000, // Rent + initial balance
-            data: vec![0; account_size],
-            owner: program_id,
+            owner: system_program::id(),
             ..Account::default()
         },
     );
@@ -246,16 +245,14 @@ async fn test_replenish_token() {
     let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
 
     // Initialize the faucet
-    let init_instruction = Instruction::new_with_borsh(
-        program_id,
-        &FaucetInstruction::Initialize {
-            distribution_amount,
-        },
-        vec![
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(admin_keypair.pubkey(), true),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-        ],
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
     );
 
     let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
@@ -269,14 +266,11 @@ async fn test_replenish_token() {
 
     // Replenish tokens
     let replenish_amount = 5000;
-    let replenish_instruction = Instruction::new_with_borsh(
-        program_id,
-        &FaucetInstruction::ReplenishTokens { replenish_amount },
-        vec![
-            AccountMeta::new(faucet_keypair.pubkey(), false),
-            AccountMeta::new(admin_keypair.pubkey(), true),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
+    let replenish_instruction = instruction::replenish(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        replenish_amount,
     );
 
     let mut transaction =
@@ -287,11 +281,7 @@ async fn test_replenish_token() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Check balances
-    let faucet_account = banks_client
-        .get_account(faucet_keypair.pubkey())
-        .await
-        .unwrap()
-        .unwrap();
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
 
     let admin_account = banks_client
         .get_account(admin_keypair.pubkey())
@@ -305,3 +295,2338 @@ async fn test_replenish_token() {
     );
     assert_eq!(admin_account.lamports, 1_000_000_000 - replenish_amount);
 }
+
+#[tokio::test]
+async fn test_request_token_cooldown() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_request_token_cooldown",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let user_keypair = Keypair::new();
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+
+    let distribution_amount = 1000;
+    let cooldown_slots = 100;
+
+    let rent = Rent::default();
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN);
+
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: faucet_account_rent + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    program_test.add_account(
+        user_keypair.pubkey(),
+        Account {
+            lamports: 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    // Context is needed to warp the clock
+    let mut context = program_test.start_with_context().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        cooldown_slots,
+        false,
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[init_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer, &admin_keypair], context.last_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let request_instruction = instruction::request_tokens(
+        &program_id,
+        &faucet,
+        &user_keypair.pubkey(),
+        &user_keypair.pubkey(),
+    );
+
+    // First claim goes through
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&request_instruction),
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &user_keypair], context.last_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let first_claim_slot = context.banks_client.get_root_slot().await.unwrap();
+
+    // Second claim inside the cooldown window is rejected
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&request_instruction),
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &user_keypair], recent_blockhash);
+
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(FaucetError::CooldownActive as u32)
+        )
+    );
+
+    // Once the window is over the recipient can claim again
+    context
+        .warp_to_slot(first_claim_slot + cooldown_slots + 1)
+        .unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[request_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer, &user_keypair], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let faucet_account = context
+        .banks_client
+        .get_account(faucet)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        faucet_account.lamports,
+        faucet_account_rent + 10_000_000 - 2 * distribution_amount
+    );
+}
+
+fn assert_faucet_error(result: Result<(), BanksClientError>, expected: FaucetError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected as u32))
+    );
+}
+
+#[test]
+fn test_error_codes_are_stable() {
+    let expected = [
+        (FaucetError::CooldownActive, 0),
+        (FaucetError::InvalidInstruction, 1),
+        (FaucetError::IncorrectOwner, 2),
+        (FaucetError::MissingSignature, 3),
+        (FaucetError::NotAdmin, 4),
+        (FaucetError::InsufficientFunds, 5),
+        (FaucetError::InvalidFaucetData, 6),
+        (FaucetError::AlreadyInitialized, 7),
+        (FaucetError::NotRentExempt, 8),
+        (FaucetError::InvalidClaimRecord, 9),
+        (FaucetError::IncorrectAccountCount, 10),
+        (FaucetError::AmountOverflow, 11),
+        (FaucetError::UninitializedFaucet, 12),
+        (FaucetError::InvalidRecipient, 13),
+        (FaucetError::NotPendingAdmin, 14),
+        (FaucetError::InvalidDestination, 15),
+        (FaucetError::FaucetPaused, 16),
+        (FaucetError::InvalidFaucetAddress, 17),
+        (FaucetError::InvalidTokenProgram, 18),
+        (FaucetError::InvalidMint, 19),
+        (FaucetError::InvalidTokenAccount, 20),
+        (FaucetError::InvalidMintAuthority, 21),
+        (FaucetError::UnsupportedFaucetMode, 22),
+        (FaucetError::InvalidMemoProgram, 23),
+        (FaucetError::InvalidFaucetName, 24),
+        (FaucetError::WindowCapReached, 25),
+        (FaucetError::ClaimLimitReached, 26),
+        (FaucetError::NotAllowlisted, 27),
+        (FaucetError::InvalidAllowlistEntry, 28),
+        (FaucetError::InvalidMerkleProof, 29),
+        (FaucetError::AlreadyClaimed, 30),
+        (FaucetError::InvalidClaimReceipt, 31),
+        (FaucetError::Denylisted, 32),
+        (FaucetError::InvalidDenylistEntry, 33),
+        (FaucetError::InvalidAttestation, 34),
+        (FaucetError::AttestationExpired, 35),
+        (FaucetError::NonceAlreadyUsed, 36),
+        (FaucetError::InvalidNonceAccount, 37),
+        (FaucetError::InvalidWindowConfig, 38),
+    ];
+
+    for (error, code) in expected {
+        assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+        assert_eq!(FaucetError::from_code(code), Some(error));
+    }
+    assert_eq!(FaucetError::from_code(expected.len() as u32), None);
+}
+
+#[tokio::test]
+async fn test_faucet_errors() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_errors",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let foreign_keypair = Keypair::new();
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let user_keypair = Keypair::new();
+
+    let rent = Rent::default();
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN);
+
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: faucet_account_rent,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    // Same layout, but not the faucet PDA and not owned by the program
+    program_test.add_account(
+        foreign_keypair.pubkey(),
+        Account {
+            lamports: faucet_account_rent,
+            data: vec![0; FaucetState::LEN],
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &user_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = |faucet: Pubkey, distribution_amount| {
+        instruction::initialize(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+            FAUCET_NAME,
+            distribution_amount,
+            0,
+            false,
+        )
+    };
+
+    // Malformed instruction data
+    let malformed_instruction = Instruction::new_with_bytes(
+        program_id,
+        &[0, 1, 2],
+        vec![AccountMeta::new(faucet, false)],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[malformed_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidInstruction,
+    );
+
+    // Faucet account that is not the PDA of the admin
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction(foreign_keypair.pubkey(), 1000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidFaucetAddress,
+    );
+
+    // Faucet account not owned by the program
+    let replenish_instruction = instruction::replenish(
+        &program_id,
+        &foreign_keypair.pubkey(),
+        &admin_keypair.pubkey(),
+        5000,
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[replenish_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::IncorrectOwner,
+    );
+
+    // Distribution amount larger than the faucet balance
+    let distribution_amount = faucet_account_rent + 1;
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction(faucet, distribution_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let request_instruction = instruction::request_tokens(
+        &program_id,
+        &faucet,
+        &user_keypair.pubkey(),
+        &user_keypair.pubkey(),
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[request_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InsufficientFunds,
+    );
+
+    // Replenish signed by someone else than the admin
+    let replenish_instruction =
+        instruction::replenish(&program_id, &faucet, &user_keypair.pubkey(), 5000);
+    let mut transaction =
+        Transaction::new_with_payer(&[replenish_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+}
+
+#[tokio::test]
+async fn test_reinitialize_hijack_is_rejected() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_reinitialize",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let attacker_keypair = Keypair::new();
+    let user_keypair = Keypair::new();
+
+    let rent = Rent::default();
+
+    // Program-owned but never initialized
+    let uninitialized = Pubkey::new_unique();
+    program_test.add_account(
+        uninitialized,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN),
+            data: vec![0; FaucetState::LEN],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN) + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &attacker_keypair, &user_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = |admin: Pubkey, distribution_amount| {
+        instruction::initialize(
+            &program_id,
+            &faucet,
+            &admin,
+            FAUCET_NAME,
+            distribution_amount,
+            0,
+            false,
+        )
+    };
+
+    // Claims from a faucet that was never initialized are refused
+    let request_instruction = instruction::request_tokens(
+        &program_id,
+        &uninitialized,
+        &user_keypair.pubkey(),
+        &user_keypair.pubkey(),
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[request_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::UninitializedFaucet,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction(admin_keypair.pubkey(), 1000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The attacker signs as "admin" of the live faucet, which is not its PDA
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction(attacker_keypair.pubkey(), 10_000_000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &attacker_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidFaucetAddress,
+    );
+
+    // The original admin can't re-initialize either
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction(admin_keypair.pubkey(), 2000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::AlreadyInitialized,
+    );
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    assert_eq!(faucet_state.admin, admin_keypair.pubkey());
+    assert_eq!(faucet_state.distribution_amount, 1000);
+}
+
+#[tokio::test]
+async fn test_request_token_keeps_rent_reserve() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_rent_reserve",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let first_user_keypair = Keypair::new();
+    let second_user_keypair = Keypair::new();
+
+    let distribution_amount = 1000;
+
+    // Just enough above rent for a single payout
+    let rent = Rent::default();
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN);
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: faucet_account_rent + distribution_amount + 500,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &first_user_keypair, &second_user_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let request_instruction =
+        |user: Pubkey| instruction::request_tokens(&program_id, &faucet, &user, &user);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[request_instruction(first_user_keypair.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &first_user_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Only 500 lamports are left above rent, the second payout is refused
+    let mut transaction = Transaction::new_with_payer(
+        &[request_instruction(second_user_keypair.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &second_user_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InsufficientFunds,
+    );
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    assert_eq!(faucet_account.lamports, faucet_account_rent + 500);
+    assert!(rent.is_exempt(faucet_account.lamports, faucet_account.data.len()));
+}
+
+#[tokio::test]
+async fn test_request_token_recipient_validation() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_recipient_validation",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let relayer_keypair = Keypair::new();
+    let empty_wallet = Pubkey::new_unique(); // never signs, holds no lamports
+    let program_owned = Pubkey::new_unique();
+
+    // Enough to make a fresh wallet rent-exempt
+    let distribution_amount = 1_000_000;
+
+    let rent = Rent::default();
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN);
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: faucet_account_rent + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    program_test.add_account(
+        program_owned,
+        Account {
+            lamports: rent.minimum_balance(0),
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &relayer_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let relayer = relayer_keypair.pubkey();
+
+    // The faucet can't be its own recipient
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::request_tokens(
+            &program_id,
+            &faucet,
+            &faucet,
+            &relayer,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &relayer_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidRecipient,
+    );
+
+    // Program-owned recipients are refused by default
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::request_tokens(
+            &program_id,
+            &faucet,
+            &program_owned,
+            &relayer,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &relayer_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidRecipient,
+    );
+
+    // Read-only recipients are refused
+    let mut readonly_instruction =
+        instruction::request_tokens(&program_id, &faucet, &empty_wallet, &relayer);
+    readonly_instruction.accounts[1] = AccountMeta::new_readonly(empty_wallet, false);
+    let mut transaction =
+        Transaction::new_with_payer(&[readonly_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &relayer_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidRecipient,
+    );
+
+    // The payer has to sign
+    let mut unsigned_instruction =
+        instruction::request_tokens(&program_id, &faucet, &empty_wallet, &relayer);
+    unsigned_instruction.accounts[3] = AccountMeta::new(relayer, false);
+    let mut transaction =
+        Transaction::new_with_payer(&[unsigned_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::MissingSignature,
+    );
+
+    // A relayer claims for an empty wallet and pays its claim record
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::request_tokens(
+            &program_id,
+            &faucet,
+            &empty_wallet,
+            &relayer,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &relayer_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_account = banks_client
+        .get_account(empty_wallet)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(recipient_account.lamports, distribution_amount);
+
+    let relayer_account = banks_client.get_account(relayer).await.unwrap().unwrap();
+    assert_eq!(
+        relayer_account.lamports,
+        1_000_000_000 - rent.minimum_balance(ClaimRecord::LEN)
+    );
+}
+
+#[tokio::test]
+async fn test_update_config() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_update_config",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let user_keypair = Keypair::new();
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN),
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &user_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        1000,
+        100,
+        false,
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let update_config_instruction = |admin: Pubkey| {
+        instruction::update_config(
+            &program_id,
+            &faucet,
+            &admin,
+            ConfigUpdate {
+                distribution_amount: Some(5000),
+                allow_program_owned_recipients: Some(true),
+                ..ConfigUpdate::default()
+            },
+        )
+    };
+
+    // Only the admin can change the configuration
+    let mut transaction = Transaction::new_with_payer(
+        &[update_config_instruction(user_keypair.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[update_config_instruction(admin_keypair.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+
+    // The cooldown was not supplied and stays as it was
+    assert_eq!(faucet_state.distribution_amount, 5000);
+    assert_eq!(faucet_state.cooldown_slots, 100);
+    assert!(faucet_state.allow_program_owned_recipients);
+    assert_eq!(faucet_state.admin, admin_keypair.pubkey());
+}
+
+#[tokio::test]
+async fn test_admin_transfer() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_admin_transfer",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let new_admin_keypair = Keypair::new();
+    let other_keypair = Keypair::new();
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN),
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &new_admin_keypair, &other_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        1000,
+        0,
+        false,
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let propose = |admin: Pubkey| {
+        instruction::propose_admin(&program_id, &faucet, &admin, &new_admin_keypair.pubkey())
+    };
+    let accept = |signer: Pubkey| instruction::accept_admin(&program_id, &faucet, &signer);
+
+    // Only the admin can propose
+    let mut transaction =
+        Transaction::new_with_payer(&[propose(other_keypair.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &other_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[propose(admin_keypair.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Someone else can't accept the proposal
+    let mut transaction =
+        Transaction::new_with_payer(&[accept(other_keypair.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &other_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotPendingAdmin,
+    );
+
+    // The admin cancels, the proposed admin can't accept anymore
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::cancel_admin_proposal(&program_id, &faucet, &admin_keypair.pubkey()),
+            accept(new_admin_keypair.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &admin_keypair, &new_admin_keypair],
+        recent_blockhash,
+    );
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(FaucetError::NotPendingAdmin as u32)
+        )
+    );
+
+    // Propose again and accept
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            propose(admin_keypair.pubkey()),
+            accept(new_admin_keypair.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &admin_keypair, &new_admin_keypair],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    assert_eq!(faucet_state.admin, new_admin_keypair.pubkey());
+    assert_eq!(faucet_state.pending_admin, None);
+
+    // The previous admin lost its rights
+    let update_config = instruction::update_config(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        ConfigUpdate {
+            distribution_amount: Some(1),
+            ..ConfigUpdate::default()
+        },
+    );
+    let mut transaction = Transaction::new_with_payer(&[update_config], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_and_close() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_withdraw_and_close",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let other_keypair = Keypair::new();
+    let destination = Pubkey::new_unique();
+
+    let rent = Rent::default();
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN);
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: faucet_account_rent + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    program_test.add_account(
+        destination,
+        Account {
+            lamports: rent.minimum_balance(0),
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &other_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        1000,
+        0,
+        false,
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let withdraw = |amount: u64, admin: Pubkey| {
+        instruction::withdraw(&program_id, &faucet, &admin, &destination, amount)
+    };
+
+    // Only the admin can withdraw
+    let mut transaction = Transaction::new_with_payer(
+        &[withdraw(1000, other_keypair.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &other_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    // Withdrawing into the rent reserve is refused
+    let mut transaction = Transaction::new_with_payer(
+        &[withdraw(10_000_001, admin_keypair.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InsufficientFunds,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[withdraw(4_000_000, admin_keypair.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    assert_eq!(faucet_account.lamports, faucet_account_rent + 6_000_000);
+
+    // Closing sends everything, rent included, to the destination
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::close_faucet(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+            &destination,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert!(banks_client.get_account(faucet).await.unwrap().is_none());
+
+    let destination_account = banks_client
+        .get_account(destination)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        destination_account.lamports,
+        rent.minimum_balance(0) + 4_000_000 + faucet_account_rent + 6_000_000
+    );
+}
+
+#[tokio::test]
+async fn test_pause_and_unpause() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_pause",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let user_keypair = Keypair::new();
+
+    let distribution_amount = 1000;
+
+    let rent = Rent::default();
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN);
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: faucet_account_rent + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    for keypair in [&admin_keypair, &user_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let request_instruction = instruction::request_tokens(
+        &program_id,
+        &faucet,
+        &user_keypair.pubkey(),
+        &user_keypair.pubkey(),
+    );
+
+    // Only the admin can pause
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::pause(
+            &program_id,
+            &faucet,
+            &user_keypair.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::pause(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&request_instruction),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::FaucetPaused,
+    );
+
+    // Replenishing still works while paused
+    let replenish_instruction =
+        instruction::replenish(&program_id, &faucet, &admin_keypair.pubkey(), 5000);
+    let mut transaction =
+        Transaction::new_with_payer(&[replenish_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::unpause(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[request_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    assert!(!FaucetState::unpack(&faucet_account.data).unwrap().paused);
+    assert_eq!(
+        faucet_account.lamports,
+        faucet_account_rent + 10_000_000 + 5000 - distribution_amount
+    );
+}
+
+#[tokio::test]
+async fn test_named_faucets() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_named",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let user_keypair = Keypair::new();
+    let (small_faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), "small");
+    let (large_faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), "large");
+    assert_ne!(small_faucet, large_faucet);
+
+    let rent = Rent::default();
+    for faucet in [small_faucet, large_faucet] {
+        program_test.add_account(
+            faucet,
+            Account {
+                lamports: rent.minimum_balance(FaucetState::LEN) + 10_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+    for keypair in [&admin_keypair, &user_keypair] {
+        program_test.add_account(
+            keypair.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                owner: system_program::id(),
+                ..Account::default()
+            },
+        );
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = |faucet: Pubkey, name: &str, distribution_amount: u64| {
+        instruction::initialize(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+            name,
+            distribution_amount,
+            1_000,
+            false,
+        )
+    };
+
+    // The name is part of the seeds, another name's address is rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction(small_faucet, "large", 1_000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidFaucetAddress,
+    );
+
+    let long_name = "n".repeat(MAX_FAUCET_NAME_LEN + 1);
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction(small_faucet, &long_name, 1_000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidFaucetName,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            init_instruction(small_faucet, "small", 1_000),
+            init_instruction(large_faucet, "large", 5_000),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    for (faucet, name, distribution_amount) in [
+        (small_faucet, "small", 1_000),
+        (large_faucet, "large", 5_000),
+    ] {
+        let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+        // Clients find faucets by their first byte
+        assert_eq!(faucet_account.data[0], AccountType::Faucet as u8);
+        let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+        assert_eq!(faucet_state.name, name);
+        assert_eq!(faucet_state.distribution_amount, distribution_amount);
+    }
+
+    // Each faucet keeps its own cooldown for the same recipient
+    let user_balance = banks_client
+        .get_balance(user_keypair.pubkey())
+        .await
+        .unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::request_tokens(
+                &program_id,
+                &small_faucet,
+                &user_keypair.pubkey(),
+                &payer.pubkey(),
+            ),
+            instruction::request_tokens(
+                &program_id,
+                &large_faucet,
+                &user_keypair.pubkey(),
+                &payer.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        banks_client
+            .get_balance(user_keypair.pubkey())
+            .await
+            .unwrap(),
+        user_balance + 6_000
+    );
+}
+
+#[tokio::test]
+async fn test_window_cap() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_window_cap",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN) + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let mut context = program_test.start_with_context().await;
+
+    let window_slots = 100;
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::initialize(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+            FAUCET_NAME,
+            1_000_000,
+            0,
+            false,
+        )],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin_keypair], context.last_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // A window without a cap would refuse every claim
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::update_config(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+            ConfigUpdate {
+                window_slots: Some(window_slots),
+                ..ConfigUpdate::default()
+            },
+        )],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin_keypair], context.last_blockhash);
+    assert_faucet_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidWindowConfig,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            // Two claims fit in a window
+            instruction::update_config(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                ConfigUpdate {
+                    window_slots: Some(window_slots),
+                    window_cap: Some(2_500_000),
+                    ..ConfigUpdate::default()
+                },
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin_keypair], context.last_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Fresh recipients only share the window cap
+    let payer = context.payer.pubkey();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::request_tokens(&program_id, &faucet, &Pubkey::new_unique(), &payer),
+            instruction::request_tokens(&program_id, &faucet, &Pubkey::new_unique(), &payer),
+        ],
+        Some(&payer),
+    );
+    transaction.sign(&[&context.payer], context.last_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let faucet_account = context
+        .banks_client
+        .get_account(faucet)
+        .await
+        .unwrap()
+        .unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    assert_eq!(faucet_state.window_distributed, 2_000_000);
+    let window_start_slot = faucet_state.window_start_slot;
+
+    let late_recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::request_tokens(
+            &program_id,
+            &faucet,
+            &late_recipient,
+            &payer,
+        )],
+        Some(&payer),
+    );
+    transaction.sign(&[&context.payer], context.last_blockhash);
+    assert_faucet_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::WindowCapReached,
+    );
+
+    // The counter resets once the window rolls over
+    context
+        .warp_to_slot(window_start_slot + window_slots)
+        .unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::request_tokens(
+            &program_id,
+            &faucet,
+            &late_recipient,
+            &payer,
+        )],
+        Some(&payer),
+    );
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let faucet_account = context
+        .banks_client
+        .get_account(faucet)
+        .await
+        .unwrap()
+        .unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    assert_eq!(faucet_state.window_distributed, 1_000_000);
+    assert!(faucet_state.window_start_slot >= window_start_slot + window_slots);
+}
+
+#[tokio::test]
+async fn test_recipient_lifetime_limits() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_lifetime_limits",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let recipient = Pubkey::new_unique();
+    let (claim_record, _) = find_claim_record_address(&program_id, &faucet, &recipient);
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN) + 100_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let update_limits = |claim_limit: u64, amount_limit: u64| {
+        instruction::update_config(
+            &program_id,
+            &faucet,
+            &admin_keypair.pubkey(),
+            ConfigUpdate {
+                recipient_claim_limit: Some(claim_limit),
+                recipient_amount_limit: Some(amount_limit),
+                ..ConfigUpdate::default()
+            },
+        )
+    };
+    let set_allowance = |admin: Pubkey, reset: bool, extra_claims: u64| {
+        instruction::set_recipient_allowance(
+            &program_id,
+            &faucet,
+            &admin,
+            &recipient,
+            reset,
+            extra_claims,
+            0,
+        )
+    };
+    let request = instruction::request_tokens(&program_id, &faucet, &recipient, &payer);
+
+    // Two claims in a lifetime, no cooldown between them
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::initialize(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                FAUCET_NAME,
+                1_000_000,
+                0,
+                false,
+            ),
+            update_limits(2, 0),
+            request.clone(),
+            request.clone(),
+        ],
+        Some(&payer),
+    );
+    transaction.sign(&[&context.payer, &admin_keypair], context.last_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(std::slice::from_ref(&request), Some(&payer));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    assert_faucet_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::ClaimLimitReached,
+    );
+
+    // Only the admin changes allowances
+    let attacker_keypair = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_allowance(attacker_keypair.pubkey(), true, 10)],
+        Some(&payer),
+    );
+    transaction.sign(&[&context.payer, &attacker_keypair], recent_blockhash);
+    assert_faucet_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    // One extra claim on top of the faucet limit
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_allowance(admin_keypair.pubkey(), false, 1),
+            request.clone(),
+        ],
+        Some(&payer),
+    );
+    transaction.sign(&[&context.payer, &admin_keypair], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_record_account = context
+        .banks_client
+        .get_account(claim_record)
+        .await
+        .unwrap()
+        .unwrap();
+    let record = ClaimRecord::try_from_slice(&claim_record_account.data).unwrap();
+    assert_eq!(record.claim_count, 3);
+    assert_eq!(record.total_claimed, 3_000_000);
+    assert_eq!(record.extra_claims, 1);
+
+    // A reset forgets past claims, the amount limit applies as well
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_allowance(admin_keypair.pubkey(), true, 0),
+            update_limits(0, 1_500_000),
+            request.clone(),
+        ],
+        Some(&payer),
+    );
+    transaction.sign(&[&context.payer, &admin_keypair], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[request], Some(&payer));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    assert_faucet_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::ClaimLimitReached,
+    );
+
+    let claim_record_account = context
+        .banks_client
+        .get_account(claim_record)
+        .await
+        .unwrap()
+        .unwrap();
+    let record = ClaimRecord::try_from_slice(&claim_record_account.data).unwrap();
+    assert_eq!(record.claim_count, 1);
+    assert_eq!(record.total_claimed, 1_000_000);
+}
+
+#[tokio::test]
+async fn test_allowlist() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_allowlist",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let allowed = Pubkey::new_unique();
+    let removed = Pubkey::new_unique();
+    let never_listed = Pubkey::new_unique();
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN) + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let admin = admin_keypair.pubkey();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::initialize(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                FAUCET_NAME,
+                1_000_000,
+                0,
+                false,
+            ),
+            instruction::update_config(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                ConfigUpdate {
+                    allowlist_enabled: Some(true),
+                    ..ConfigUpdate::default()
+                },
+            ),
+            instruction::add_to_allowlist(&program_id, &faucet, &admin, &allowed),
+            instruction::add_to_allowlist(&program_id, &faucet, &admin, &removed),
+            instruction::remove_from_allowlist(&program_id, &faucet, &admin, &removed),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Claims built from the faucet state carry the allowlist entry
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    let allowlisted_request = |recipient: Pubkey| {
+        instruction::request_tokens_for(
+            &program_id,
+            &faucet,
+            &faucet_state,
+            &recipient,
+            &payer.pubkey(),
+            &ClaimOptions::default(),
+        )
+        .unwrap()
+    };
+
+    // Allowed wallets claim as usual
+    let mut transaction =
+        Transaction::new_with_payer(&[allowlisted_request(allowed)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(banks_client.get_balance(allowed).await.unwrap(), 1_000_000);
+
+    // Removed and never-listed wallets are turned away
+    for wallet in [removed, never_listed] {
+        let mut transaction =
+            Transaction::new_with_payer(&[allowlisted_request(wallet)], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        assert_faucet_error(
+            banks_client.process_transaction(transaction).await,
+            FaucetError::NotAllowlisted,
+        );
+    }
+
+    // The entry of another wallet doesn't count
+    let (allowed_entry, _) = find_allowlist_entry_address(&program_id, &faucet, &allowed);
+    let mut instruction =
+        instruction::request_tokens(&program_id, &faucet, &never_listed, &payer.pubkey());
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(allowed_entry, false));
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidAllowlistEntry,
+    );
+
+    // Without the entry account the claim has the wrong shape
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::request_tokens(
+            &program_id,
+            &faucet,
+            &allowed,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::IncorrectAccountCount,
+    );
+}
+
+#[tokio::test]
+async fn test_merkle_allowlist() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_merkle",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let leaves: Vec<(Pubkey, u64)> = (1..=5)
+        .map(|i| (Pubkey::new_unique(), i * 1_000_000))
+        .collect();
+    let tree = MerkleTree::new(&leaves);
+    let merkle_root = tree.root().unwrap();
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN) + 20_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::initialize(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                FAUCET_NAME,
+                1_000_000,
+                0,
+                false,
+            ),
+            instruction::set_merkle_root(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                Some(merkle_root),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    let merkle_request = |recipient: Pubkey, amount: u64, proof: Vec<[u8; 32]>, payer: Pubkey| {
+        instruction::request_tokens_for(
+            &program_id,
+            &faucet,
+            &faucet_state,
+            &recipient,
+            &payer,
+            &ClaimOptions {
+                merkle_claim: Some(MerkleClaim { amount, proof }),
+                ..ClaimOptions::default()
+            },
+        )
+        .unwrap()
+    };
+
+    // Claims without a proof aren't built
+    assert_eq!(
+        instruction::request_tokens_for(
+            &program_id,
+            &faucet,
+            &faucet_state,
+            &Pubkey::new_unique(),
+            &payer.pubkey(),
+            &ClaimOptions::default(),
+        ),
+        Err(MissingClaimOption::MerkleClaim)
+    );
+
+    // Only the admin sets the root
+    let intruder = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::set_merkle_root(
+            &program_id,
+            &faucet,
+            &intruder.pubkey(),
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &intruder], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    // Every leaf claims its own amount, not `distribution_amount`
+    for (index, (wallet, amount)) in leaves.iter().enumerate() {
+        let mut transaction = Transaction::new_with_payer(
+            &[merkle_request(
+                *wallet,
+                *amount,
+                tree.proof(index).unwrap(),
+                payer.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+        assert_eq!(banks_client.get_balance(*wallet).await.unwrap(), *amount);
+    }
+
+    let (claim_receipt, _) =
+        find_claim_receipt_address(&program_id, &faucet, &merkle_root, &leaves[0].0);
+    let claim_receipt_account = banks_client
+        .get_account(claim_receipt)
+        .await
+        .unwrap()
+        .unwrap();
+    let receipt = ClaimReceipt::try_from_slice(&claim_receipt_account.data).unwrap();
+    assert_eq!(receipt.account_type, AccountType::ClaimReceipt);
+    assert_eq!(receipt.amount, leaves[0].1);
+
+    // A leaf pays out once, the admin pays to make the transaction distinct
+    let (wallet, amount) = leaves[0];
+    let mut transaction = Transaction::new_with_payer(
+        &[merkle_request(
+            wallet,
+            amount,
+            tree.proof(0).unwrap(),
+            admin_keypair.pubkey(),
+        )],
+        Some(&admin_keypair.pubkey()),
+    );
+    transaction.sign(&[&admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::AlreadyClaimed,
+    );
+
+    // Another amount or another wallet doesn't match the proof
+    let outsider = Pubkey::new_unique();
+    for (wallet, amount) in [(leaves[1].0, leaves[1].1 + 1), (outsider, leaves[1].1)] {
+        let mut transaction = Transaction::new_with_payer(
+            &[merkle_request(
+                wallet,
+                amount,
+                tree.proof(1).unwrap(),
+                payer.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        assert_faucet_error(
+            banks_client.process_transaction(transaction).await,
+            FaucetError::InvalidMerkleProof,
+        );
+    }
+
+    // Claims without a proof are turned away while the root is set
+    let mut instruction =
+        instruction::request_tokens(&program_id, &faucet, &outsider, &payer.pubkey());
+    let (claim_receipt, _) =
+        find_claim_receipt_address(&program_id, &faucet, &merkle_root, &outsider);
+    instruction
+        .accounts
+        .push(AccountMeta::new(claim_receipt, false));
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidMerkleProof,
+    );
+}
+
+#[tokio::test]
+async fn test_denylist() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_denylist",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let blocked = Pubkey::new_unique();
+    let unblocked = Pubkey::new_unique();
+    let honest = Pubkey::new_unique();
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN) + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let deny = |wallet: Pubkey| {
+        instruction::add_to_denylist(&program_id, &faucet, &admin_keypair.pubkey(), &wallet)
+    };
+    let undeny = |wallet: Pubkey| {
+        instruction::remove_from_denylist(&program_id, &faucet, &admin_keypair.pubkey(), &wallet)
+    };
+    let checked_request = |recipient: Pubkey, denylist_entry: Pubkey| {
+        let mut instruction =
+            instruction::request_tokens(&program_id, &faucet, &recipient, &payer.pubkey());
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(denylist_entry, false));
+        instruction
+    };
+    let denylist_len = |data: Vec<u8>| FaucetState::unpack(&data).unwrap().denylist_len;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::initialize(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                FAUCET_NAME,
+                1_000_000,
+                0,
+                false,
+            ),
+            deny(blocked),
+            deny(blocked),
+            deny(unblocked),
+            undeny(unblocked),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Adding twice counts once, removing lifts the block
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    assert_eq!(denylist_len(faucet_account.data), 1);
+
+    let (blocked_entry, _) = find_denylist_entry_address(&program_id, &faucet, &blocked);
+    let mut transaction = Transaction::new_with_payer(
+        &[checked_request(blocked, blocked_entry)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::Denylisted,
+    );
+
+    // Everyone else claims with their own, empty, entry
+    for wallet in [unblocked, honest] {
+        let (denylist_entry, _) = find_denylist_entry_address(&program_id, &faucet, &wallet);
+        let mut transaction = Transaction::new_with_payer(
+            &[checked_request(wallet, denylist_entry)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+        assert_eq!(banks_client.get_balance(wallet).await.unwrap(), 1_000_000);
+    }
+
+    // The entry of another wallet doesn't count
+    let (honest_entry, _) = find_denylist_entry_address(&program_id, &faucet, &honest);
+    let mut transaction = Transaction::new_with_payer(
+        &[checked_request(blocked, honest_entry)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidDenylistEntry,
+    );
+
+    // Removing a wallet that isn't blocked is a no-op
+    let mut transaction = Transaction::new_with_payer(&[undeny(honest)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    assert_eq!(denylist_len(faucet_account.data), 1);
+
+    // Once the denylist is empty claims no longer need the entry account
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            undeny(blocked),
+            instruction::request_tokens(&program_id, &faucet, &blocked, &payer.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(banks_client.get_balance(blocked).await.unwrap(), 1_000_000);
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    assert_eq!(denylist_len(faucet_account.data), 0);
+}
+
+#[tokio::test]
+async fn test_attested_claims() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_attestation",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let attester = Keypair::new();
+    let rotated_attester = Keypair::new();
+    let distribution_amount = 1_000_000;
+
+    let rent = Rent::default();
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: rent.minimum_balance(FaucetState::LEN) + 10_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    context.warp_to_slot(100).unwrap();
+
+    let set_attester_instruction = |attester: Option<Pubkey>, admin: Pubkey| {
+        instruction::set_attester(&program_id, &faucet, &admin, attester)
+    };
+    let attestation = |recipient: Pubkey, nonce: u64| Attestation {
+        faucet,
+        recipient,
+        amount: distribution_amount,
+        nonce,
+        expiry_slot: 1_000,
+    };
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::initialize(
+                &program_id,
+                &faucet,
+                &admin_keypair.pubkey(),
+                FAUCET_NAME,
+                distribution_amount,
+                0,
+                false,
+            ),
+            set_attester_instruction(Some(attester.pubkey()), admin_keypair.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The Ed25519 check of `attestation` signed by `signer`, then the claim it attests
+    let faucet_account = context
+        .banks_client
+        .get_account(faucet)
+        .await
+        .unwrap()
+        .unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    let attested_request = |signer: &Keypair, attestation: &Attestation| {
+        let message = attestation.message();
+        let signature = signer.sign_message(&message);
+        [
+            new_ed25519_instruction(&signer.pubkey(), &signature.into(), &message),
+            instruction::request_tokens_for(
+                &program_id,
+                &faucet,
+                &faucet_state,
+                &attestation.recipient,
+                &payer.pubkey(),
+                &ClaimOptions {
+                    attestation_nonce: Some(attestation.nonce),
+                    ..ClaimOptions::default()
+                },
+            )
+            .unwrap(),
+        ]
+    };
+
+    // Nor claims without an attestation nonce
+    assert_eq!(
+        instruction::request_tokens_for(
+            &program_id,
+            &faucet,
+            &faucet_state,
+            &Pubkey::new_unique(),
+            &payer.pubkey(),
+            &ClaimOptions::default(),
+        ),
+        Err(MissingClaimOption::AttestationNonce)
+    );
+
+    // Errors of the claim, which comes after the Ed25519 instruction
+    let assert_claim_error = |result: Result<(), BanksClientError>, expected: FaucetError| {
+        assert_eq!(
+            result.unwrap_err().unwrap(),
+            TransactionError::InstructionError(1, InstructionError::Custom(expected as u32))
+        );
+    };
+
+    // Only the admin rotates the attester
+    let intruder = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_attester_instruction(None, intruder.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &intruder], recent_blockhash);
+    assert_faucet_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    // A fresh attestation lets the recipient claim
+    let recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &attested_request(&attester, &attestation(recipient, 1)),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        context.banks_client.get_balance(recipient).await.unwrap(),
+        distribution_amount
+    );
+
+    // A used nonce is rejected, also for another recipient
+    let other_recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &attested_request(&attester, &attestation(other_recipient, 1)),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_claim_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::NonceAlreadyUsed,
+    );
+
+    // Attestations of another signer, recipient or amount don't count
+    let impostor = Keypair::new();
+    let wrong_amount = Attestation {
+        amount: distribution_amount + 1,
+        ..attestation(other_recipient, 2)
+    };
+    let mut wrong_recipient = attested_request(&attester, &attestation(other_recipient, 3));
+    let claimer = Pubkey::new_unique();
+    let (claimer_record, _) = find_claim_record_address(&program_id, &faucet, &claimer);
+    wrong_recipient[1].accounts[1] = AccountMeta::new(claimer, false);
+    wrong_recipient[1].accounts[2] = AccountMeta::new(claimer_record, false);
+    for instructions in [
+        attested_request(&impostor, &attestation(other_recipient, 2)),
+        attested_request(&attester, &wrong_amount),
+        wrong_recipient,
+    ] {
+        let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        assert_claim_error(
+            context.banks_client.process_transaction(transaction).await,
+            FaucetError::InvalidAttestation,
+        );
+    }
+
+    // The claim needs the Ed25519 instruction right before it
+    let [_, unattested] = attested_request(&attester, &attestation(other_recipient, 4));
+    let mut transaction = Transaction::new_with_payer(&[unattested], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidAttestation,
+    );
+
+    // Forged signatures fail the Ed25519 check itself
+    let mut forged = attested_request(&attester, &attestation(other_recipient, 5));
+    let message_start = forged[0].data.len() - attestation(other_recipient, 5).message().len();
+    forged[0].data[message_start + 64] ^= 1; // flips a bit of the amount
+    let mut transaction = Transaction::new_with_payer(&forged, Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+
+    // Expired attestations are rejected
+    let expired = Attestation {
+        expiry_slot: 50,
+        ..attestation(other_recipient, 6)
+    };
+    let mut transaction = Transaction::new_with_payer(
+        &attested_request(&attester, &expired),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_claim_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::AttestationExpired,
+    );
+
+    // After a rotation only the new attester counts
+    let mut transaction = Transaction::new_with_payer(
+        &[set_attester_instruction(
+            Some(rotated_attester.pubkey()),
+            admin_keypair.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &attested_request(&attester, &attestation(other_recipient, 7)),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_claim_error(
+        context.banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidAttestation,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &attested_request(&rotated_attester, &attestation(other_recipient, 7)),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(other_recipient)
+            .await
+            .unwrap(),
+        distribution_amount
+    );
+}
@@ -0,0 +1,536 @@
+use serde_json::{json, Value};
+use simple_token_faucet::error::FaucetError;
+use simple_token_faucet::instruction::{self, ClaimOptions, MerkleClaim};
+use simple_token_faucet::process_instruction;
+use simple_token_faucet::rpc::{
+    nonblocking::FaucetClient, FaucetClientError, FaucetView, NewFaucet,
+};
+use simple_token_faucet::state::{
+    find_faucet_address, try_find_faucet_address, AccountType, FaucetMode, FaucetState,
+    MAX_FAUCET_NAME_LEN,
+};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::rpc_client::RpcClient,
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+    rpc_response::{Response, RpcResponseContext, RpcSimulateTransactionResult},
+};
+use solana_program::{
+    ed25519_program,
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use std::collections::HashMap;
+
+const FAUCET_NAME: &str = "test";
+
+fn faucet_state(program_id: &Pubkey, admin: &Pubkey, mode: FaucetMode) -> FaucetState {
+    let (_, bump) = find_faucet_address(program_id, admin, FAUCET_NAME);
+    FaucetState {
+        account_type: AccountType::Faucet,
+        bump,
+        creator: *admin,
+        admin: *admin,
+        distribution_amount: 1_000,
+        cooldown_slots: 10,
+        allow_program_owned_recipients: false,
+        paused: false,
+        pending_admin: None,
+        mode,
+        mint: Pubkey::default(),
+        vault: Pubkey::default(),
+        name: FAUCET_NAME.to_string(),
+        window_slots: 0,
+        window_cap: 0,
+        window_start_slot: 0,
+        window_distributed: 0,
+        recipient_claim_limit: 0,
+        recipient_amount_limit: 0,
+        allowlist_enabled: false,
+        merkle_root: None,
+        denylist_len: 0,
+        attester: None,
+    }
+}
+
+fn account_info(address: &Pubkey, owner: &Pubkey, faucet_state: &FaucetState) -> Value {
+    let mut data = vec![0; FaucetState::LEN];
+    faucet_state.pack(&mut data).unwrap();
+    let account = Account {
+        lamports: 10_000,
+        data,
+        owner: *owner,
+        executable: false,
+        rent_epoch: 0,
+    };
+
+    json!(Response {
+        context: RpcResponseContext::new(1),
+        value: Some(UiAccount::encode(
+            address,
+            &account,
+            UiAccountEncoding::Base64,
+            None,
+            None
+        )),
+    })
+}
+
+// Every request not mocked gets the canned success of the mock sender
+fn mock_client(program_id: &Pubkey, mocks: Vec<(RpcRequest, Value)>) -> FaucetClient {
+    let mocks: HashMap<_, _> = mocks.into_iter().collect();
+    FaucetClient::new(
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks),
+        *program_id,
+    )
+}
+
+#[tokio::test]
+async fn test_get_faucet_state() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let (faucet, _) = find_faucet_address(&program_id, &admin, FAUCET_NAME);
+    let state = faucet_state(&program_id, &admin, FaucetMode::Native);
+
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&faucet, &program_id, &state),
+        )],
+    );
+    let decoded = client.get_faucet_state(&faucet).await.unwrap();
+    assert_eq!(decoded.admin, admin);
+    assert_eq!(decoded.name, FAUCET_NAME);
+    assert_eq!(decoded.distribution_amount, state.distribution_amount);
+    assert_eq!(decoded.mode, FaucetMode::Native);
+
+    // The mock sender answers unmocked accounts with nothing
+    assert!(matches!(
+        client.get_faucet_state(&faucet).await,
+        Err(FaucetClientError::AccountNotFound(address)) if address == faucet
+    ));
+
+    let other_program = Pubkey::new_unique();
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&faucet, &other_program, &state),
+        )],
+    );
+    assert!(matches!(
+        client.get_faucet_state(&faucet).await,
+        Err(FaucetClientError::IncorrectOwner { owner, .. }) if owner == other_program
+    ));
+}
+
+#[tokio::test]
+async fn test_get_faucet() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let (faucet, _) = find_faucet_address(&program_id, &admin, FAUCET_NAME);
+    let state = faucet_state(&program_id, &admin, FaucetMode::Native);
+
+    let client = mock_client(
+        &program_id,
+        vec![
+            (
+                RpcRequest::GetAccountInfo,
+                account_info(&faucet, &program_id, &state),
+            ),
+            (RpcRequest::GetMinimumBalanceForRentExemption, json!(4_000)),
+        ],
+    );
+    let view = client.get_faucet(&faucet).await.unwrap();
+    assert_eq!(view.address, faucet);
+    assert_eq!(view.available(), Some(6_000));
+    assert_eq!(view.claims_remaining(), Some(6));
+}
+
+#[tokio::test]
+async fn test_token_program() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let state = faucet_state(&program_id, &Pubkey::new_unique(), FaucetMode::Native);
+
+    // Only the owner of the account matters
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&mint, &spl_token_2022::id(), &state),
+        )],
+    );
+    assert_eq!(
+        client.token_program(&mint).await.unwrap(),
+        spl_token_2022::id()
+    );
+    assert!(matches!(
+        client.token_program(&mint).await,
+        Err(FaucetClientError::AccountNotFound(address)) if address == mint
+    ));
+}
+
+#[tokio::test]
+async fn test_initialize() {
+    let program_id = Pubkey::new_unique();
+    let admin = Keypair::new();
+    let client = mock_client(&program_id, vec![]);
+
+    let mut new_faucet = NewFaucet {
+        name: FAUCET_NAME.to_string(),
+        distribution_amount: 1_000,
+        cooldown_slots: 10,
+        allow_program_owned_recipients: false,
+        mode: FaucetMode::Native,
+        mint: None,
+    };
+    let (faucet, _) = client.initialize(&admin, &new_faucet).await.unwrap();
+    assert_eq!(
+        faucet,
+        find_faucet_address(&program_id, &admin.pubkey(), FAUCET_NAME).0
+    );
+
+    // Names past the seed limit have no address, refused before anything is sent
+    let name = "n".repeat(MAX_FAUCET_NAME_LEN + 1);
+    assert!(try_find_faucet_address(&program_id, &admin.pubkey(), &name).is_none());
+    let long_name = NewFaucet {
+        name: name.clone(),
+        ..new_faucet.clone()
+    };
+    assert!(matches!(
+        client.initialize(&admin, &long_name).await,
+        Err(FaucetClientError::InvalidFaucetName(invalid)) if invalid == name
+    ));
+
+    // Token faucets can't be created without their mint
+    new_faucet.mode = FaucetMode::TokenVault;
+    assert!(matches!(
+        client.initialize(&admin, &new_faucet).await,
+        Err(FaucetClientError::MissingMint)
+    ));
+}
+
+#[tokio::test]
+async fn test_request_tokens() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let (faucet, _) = find_faucet_address(&program_id, &admin, FAUCET_NAME);
+    let mut state = faucet_state(&program_id, &admin, FaucetMode::Native);
+
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&faucet, &program_id, &state),
+        )],
+    );
+    client
+        .request_tokens(&faucet, &recipient, &payer, &ClaimOptions::default())
+        .await
+        .unwrap();
+
+    // Merkle faucets need a leaf proof, refused before anything is sent
+    state.merkle_root = Some([1; 32]);
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&faucet, &program_id, &state),
+        )],
+    );
+    assert!(matches!(
+        client
+            .request_tokens(&faucet, &recipient, &payer, &ClaimOptions::default())
+            .await,
+        Err(FaucetClientError::MerkleProofRequired(address)) if address == faucet
+    ));
+
+    // Attested faucets are refused even with a proof
+    state.attester = Some(Pubkey::new_unique());
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&faucet, &program_id, &state),
+        )],
+    );
+    let options = ClaimOptions {
+        merkle_claim: Some(MerkleClaim {
+            amount: 1_000,
+            proof: vec![],
+        }),
+        ..ClaimOptions::default()
+    };
+    assert!(matches!(
+        client
+            .request_tokens(&faucet, &recipient, &payer, &options)
+            .await,
+        Err(FaucetClientError::AttestationRequired(address)) if address == faucet
+    ));
+}
+
+#[tokio::test]
+async fn test_request_tokens_faucet_error() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let payer = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin, FAUCET_NAME);
+    let state = faucet_state(&program_id, &admin, FaucetMode::Native);
+
+    // The faucet instruction fails once the transaction lands
+    let err = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(FaucetError::CooldownActive as u32),
+    );
+    let statuses = json!({
+        "context": { "slot": 1 },
+        "value": [{
+            "slot": 1,
+            "confirmations": null,
+            "status": { "Err": err },
+            "err": err,
+            "confirmationStatus": "finalized",
+        }],
+    });
+    let client = mock_client(
+        &program_id,
+        vec![
+            (
+                RpcRequest::GetAccountInfo,
+                account_info(&faucet, &program_id, &state),
+            ),
+            (RpcRequest::GetSignatureStatuses, statuses),
+        ],
+    );
+    assert!(matches!(
+        client
+            .request_tokens(
+                &faucet,
+                &Pubkey::new_unique(),
+                &payer,
+                &ClaimOptions::default()
+            )
+            .await,
+        Err(FaucetClientError::Faucet(FaucetError::CooldownActive))
+    ));
+}
+
+#[tokio::test]
+async fn test_replenish() {
+    let program_id = Pubkey::new_unique();
+    let admin = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin.pubkey(), FAUCET_NAME);
+    let mut state = faucet_state(&program_id, &admin.pubkey(), FaucetMode::Native);
+
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&faucet, &program_id, &state),
+        )],
+    );
+    client.replenish(&faucet, &admin, 5_000).await.unwrap();
+
+    // Minting faucets have no vault to top up
+    state.mode = FaucetMode::MintAuthority;
+    state.mint = Pubkey::new_unique();
+    let client = mock_client(
+        &program_id,
+        vec![(
+            RpcRequest::GetAccountInfo,
+            account_info(&faucet, &program_id, &state),
+        )],
+    );
+    assert!(matches!(
+        client.replenish(&faucet, &admin, 5_000).await,
+        Err(FaucetClientError::NothingToReplenish(address)) if address == faucet
+    ));
+}
+
+#[tokio::test]
+async fn test_faucet_view() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_view",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+
+    let distribution_amount = 1000;
+
+    // Two and a half payouts above rent
+    let rent = Rent::default();
+    let faucet_account_rent = rent.minimum_balance(FaucetState::LEN);
+    program_test.add_account(
+        faucet,
+        Account {
+            lamports: faucet_account_rent + 2 * distribution_amount + 500,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let init_instruction = instruction::initialize(
+        &program_id,
+        &faucet,
+        &admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let faucet_account = banks_client.get_account(faucet).await.unwrap().unwrap();
+    let view = FaucetView::from_account(&program_id, &faucet, &faucet_account, faucet_account_rent)
+        .unwrap();
+    assert_eq!(view.state.admin, admin_keypair.pubkey());
+    assert_eq!(view.lamports, faucet_account.lamports);
+    assert_eq!(view.available(), Some(2 * distribution_amount + 500));
+    assert_eq!(view.claims_remaining(), Some(2));
+
+    // Another program's account is refused before its data is read
+    let other_program_id = Pubkey::new_unique();
+    assert!(matches!(
+        FaucetView::from_account(&other_program_id, &faucet, &faucet_account, faucet_account_rent),
+        Err(FaucetClientError::IncorrectOwner { owner, .. }) if owner == program_id
+    ));
+
+    // So is an account of the program that isn't a faucet
+    let admin_account = Account {
+        owner: program_id,
+        ..banks_client
+            .get_account(admin_keypair.pubkey())
+            .await
+            .unwrap()
+            .unwrap()
+    };
+    assert!(matches!(
+        FaucetView::from_account(&program_id, &faucet, &admin_account, faucet_account_rent),
+        Err(FaucetClientError::NotAFaucet(address)) if address == faucet
+    ));
+}
+
+#[test]
+fn test_client_error_decoding() {
+    let program_id = Pubkey::new_unique();
+    let faucet = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let token_program = spl_token::id();
+    let replenish = instruction::replenish(&program_id, &faucet, &admin, 1);
+    let cooldown = InstructionError::Custom(FaucetError::CooldownActive as u32);
+
+    // Errors the faucet instruction raised come out typed
+    let err = FaucetClientError::from_transaction_error(
+        ClientError::from(TransactionError::InstructionError(0, cooldown.clone())),
+        &program_id,
+        std::slice::from_ref(&replenish),
+    );
+    assert!(matches!(
+        err,
+        FaucetClientError::Faucet(FaucetError::CooldownActive)
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Recipient is still in its cooldown window (faucet error 0)"
+    );
+
+    // Codes of other instructions, unknown codes and transaction errors stay RPC errors
+    let precompile = Instruction::new_with_bytes(ed25519_program::id(), &[], vec![]);
+    for (transaction_error, instructions) in [
+        (
+            TransactionError::InstructionError(0, cooldown.clone()),
+            vec![precompile, replenish.clone()],
+        ),
+        (
+            TransactionError::InstructionError(0, InstructionError::Custom(u32::MAX)),
+            vec![replenish.clone()],
+        ),
+        (
+            TransactionError::InstructionError(1, cooldown.clone()),
+            vec![replenish.clone()],
+        ),
+        (TransactionError::AccountNotFound, vec![replenish.clone()]),
+    ] {
+        assert!(matches!(
+            FaucetClientError::from_transaction_error(
+                ClientError::from(transaction_error),
+                &program_id,
+                &instructions
+            ),
+            FaucetClientError::Rpc(_)
+        ));
+    }
+
+    // A program the faucet calls fails at the faucet's index, the preflight logs name it
+    let preflight_failure = |failed_program: &Pubkey| {
+        let transaction_error = TransactionError::InstructionError(0, cooldown.clone());
+        ClientError::from(RpcError::RpcResponseError {
+            code: -32002,
+            message: "Transaction simulation failed".to_string(),
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                RpcSimulateTransactionResult {
+                    err: Some(transaction_error),
+                    logs: Some(vec![
+                        format!("Program {} invoke [1]", program_id),
+                        format!("Program {} invoke [2]", failed_program),
+                        format!(
+                            "Program {} failed: custom program error: 0x0",
+                            failed_program
+                        ),
+                        format!("Program {} failed: custom program error: 0x0", program_id),
+                    ]),
+                    accounts: None,
+                    units_consumed: None,
+                    return_data: None,
+                    inner_instructions: None,
+                    replacement_blockhash: None,
+                },
+            ),
+        })
+    };
+    assert!(matches!(
+        FaucetClientError::from_transaction_error(
+            preflight_failure(&token_program),
+            &program_id,
+            std::slice::from_ref(&replenish)
+        ),
+        FaucetClientError::Rpc(_)
+    ));
+    assert!(matches!(
+        FaucetClientError::from_transaction_error(
+            preflight_failure(&program_id),
+            &program_id,
+            &[replenish]
+        ),
+        FaucetClientError::Faucet(FaucetError::CooldownActive)
+    ));
+}
@@ -0,0 +1,889 @@
+use simple_token_faucet::error::FaucetError;
+use simple_token_faucet::instruction::{self, ClaimOptions, MissingClaimOption};
+use simple_token_faucet::process_instruction;
+use simple_token_faucet::state::{find_faucet_address, FaucetMode, FaucetState};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::{create_associated_token_account, create_associated_token_account_idempotent},
+};
+use spl_token_2022::{
+    extension::{
+        memo_transfer::instruction::enable_required_transfer_memos,
+        transfer_fee::instruction::initialize_transfer_fee_config, ExtensionType,
+        StateWithExtensions,
+    },
+    instruction::{initialize_mint2, mint_to, reallocate},
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+
+const DECIMALS: u8 = 6;
+const FAUCET_NAME: &str = "token";
+
+struct TokenFaucet {
+    program_id: Pubkey,
+    admin_keypair: Keypair,
+    faucet: Pubkey,
+    mint: Pubkey,
+    vault: Pubkey,
+    admin_token_account: Pubkey,
+    token_program: Pubkey,
+}
+
+// Adds a mint and an admin token account holding `admin_tokens`, the faucet only mints in
+// `MintAuthority` mode
+fn token_program_test(
+    admin_tokens: u64,
+    mode: FaucetMode,
+    token_program: Pubkey,
+) -> (ProgramTest, TokenFaucet) {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_token_vault",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let mint = Pubkey::new_unique();
+    let vault = get_associated_token_address_with_program_id(&faucet, &mint, &token_program);
+    let admin_token_account = get_associated_token_address_with_program_id(
+        &admin_keypair.pubkey(),
+        &mint,
+        &token_program,
+    );
+    let rent = Rent::default();
+
+    program_test.add_account(
+        admin_keypair.pubkey(),
+        Account {
+            lamports: 100_000_000,
+            ..Account::default()
+        },
+    );
+
+    let mut mint_data = vec![0; Mint::LEN];
+    Mint::pack(
+        Mint {
+            mint_authority: COption::Some(if mode == FaucetMode::MintAuthority {
+                faucet
+            } else {
+                admin_keypair.pubkey()
+            }),
+            supply: admin_tokens,
+            decimals: DECIMALS,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        &mut mint_data,
+    )
+    .unwrap();
+    program_test.add_account(
+        mint,
+        Account {
+            lamports: rent.minimum_balance(Mint::LEN),
+            data: mint_data,
+            owner: token_program,
+            ..Account::default()
+        },
+    );
+
+    let mut token_data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(
+        TokenAccount {
+            mint,
+            owner: admin_keypair.pubkey(),
+            amount: admin_tokens,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        },
+        &mut token_data,
+    )
+    .unwrap();
+    program_test.add_account(
+        admin_token_account,
+        Account {
+            lamports: rent.minimum_balance(TokenAccount::LEN),
+            data: token_data,
+            owner: token_program,
+            ..Account::default()
+        },
+    );
+
+    (
+        program_test,
+        TokenFaucet {
+            program_id,
+            admin_keypair,
+            faucet,
+            mint,
+            vault,
+            admin_token_account,
+            token_program,
+        },
+    )
+}
+
+fn initialize_instruction(token_faucet: &TokenFaucet, distribution_amount: u64) -> Instruction {
+    instruction::initialize_token_vault(
+        &token_faucet.program_id,
+        &token_faucet.faucet,
+        &token_faucet.admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    )
+}
+
+fn initialize_mint_authority_instruction(
+    token_faucet: &TokenFaucet,
+    distribution_amount: u64,
+) -> Instruction {
+    instruction::initialize_mint_authority(
+        &token_faucet.program_id,
+        &token_faucet.faucet,
+        &token_faucet.admin_keypair.pubkey(),
+        FAUCET_NAME,
+        distribution_amount,
+        0,
+        false,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    )
+}
+
+fn request_minted_tokens_instruction(
+    token_faucet: &TokenFaucet,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    instruction::request_minted_tokens(
+        &token_faucet.program_id,
+        &token_faucet.faucet,
+        recipient,
+        payer,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    )
+}
+
+fn set_mint_authority_instruction(
+    token_faucet: &TokenFaucet,
+    new_authority: Pubkey,
+) -> Instruction {
+    instruction::set_mint_authority(
+        &token_faucet.program_id,
+        &token_faucet.faucet,
+        &token_faucet.admin_keypair.pubkey(),
+        &token_faucet.mint,
+        &token_faucet.token_program,
+        &new_authority,
+    )
+}
+
+fn replenish_instruction(token_faucet: &TokenFaucet, replenish_amount: u64) -> Instruction {
+    instruction::replenish_token_vault(
+        &token_faucet.program_id,
+        &token_faucet.faucet,
+        &token_faucet.admin_keypair.pubkey(),
+        &token_faucet.mint,
+        &token_faucet.token_program,
+        replenish_amount,
+    )
+}
+
+fn request_tokens_instruction(
+    token_faucet: &TokenFaucet,
+    recipient: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    instruction::request_tokens_from_vault(
+        &token_faucet.program_id,
+        &token_faucet.faucet,
+        recipient,
+        payer,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    )
+}
+
+async fn token_balance(banks_client: &mut BanksClient, token_account: Pubkey) -> u64 {
+    let account = banks_client
+        .get_account(token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    StateWithExtensions::<TokenAccount>::unpack(&account.data)
+        .unwrap()
+        .base
+        .amount
+}
+
+fn assert_faucet_error(result: Result<(), BanksClientError>, expected: FaucetError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected as u32))
+    );
+}
+
+#[tokio::test]
+async fn test_token_vault_request_and_replenish() {
+    token_vault_request_and_replenish(spl_token::id()).await;
+}
+
+#[tokio::test]
+async fn test_token_2022_vault_request_and_replenish() {
+    token_vault_request_and_replenish(spl_token_2022::id()).await;
+}
+
+async fn token_vault_request_and_replenish(token_program: Pubkey) {
+    let distribution_amount = 5 * 10u64.pow(DECIMALS as u32);
+    let (program_test, token_faucet) = token_program_test(
+        100 * 10u64.pow(DECIMALS as u32),
+        FaucetMode::TokenVault,
+        token_program,
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Initialize creates the vault as the faucet PDA's token account
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            initialize_instruction(&token_faucet, distribution_amount),
+            replenish_instruction(&token_faucet, 10 * distribution_amount),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let faucet_account = banks_client
+        .get_account(token_faucet.faucet)
+        .await
+        .unwrap()
+        .unwrap();
+    let faucet_state = FaucetState::unpack(&faucet_account.data).unwrap();
+    assert_eq!(faucet_state.mode, FaucetMode::TokenVault);
+    assert_eq!(faucet_state.mint, token_faucet.mint);
+    assert_eq!(faucet_state.vault, token_faucet.vault);
+
+    // Claims built from the faucet state need the token program of the mint
+    let claim_options = ClaimOptions {
+        token_program: Some(token_program),
+        ..ClaimOptions::default()
+    };
+    let recipient = Pubkey::new_unique();
+    assert_eq!(
+        instruction::request_tokens_for(
+            &token_faucet.program_id,
+            &token_faucet.faucet,
+            &faucet_state,
+            &recipient,
+            &payer.pubkey(),
+            &ClaimOptions::default(),
+        ),
+        Err(MissingClaimOption::TokenProgram)
+    );
+    assert_eq!(
+        instruction::request_tokens_for(
+            &token_faucet.program_id,
+            &token_faucet.faucet,
+            &faucet_state,
+            &recipient,
+            &payer.pubkey(),
+            &claim_options,
+        ),
+        Ok(request_tokens_instruction(
+            &token_faucet,
+            &recipient,
+            &payer.pubkey()
+        ))
+    );
+
+    let vault_account = banks_client
+        .get_account(token_faucet.vault)
+        .await
+        .unwrap()
+        .unwrap();
+    let vault_state = StateWithExtensions::<TokenAccount>::unpack(&vault_account.data)
+        .unwrap()
+        .base;
+    assert_eq!(vault_state.owner, token_faucet.faucet);
+    assert_eq!(vault_state.amount, 10 * distribution_amount);
+
+    // The recipient has no token account yet, the payer covers its creation
+    let recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[request_tokens_instruction(
+            &token_faucet,
+            &recipient,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_token_account = get_associated_token_address_with_program_id(
+        &recipient,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    );
+    assert_eq!(
+        token_balance(&mut banks_client, recipient_token_account).await,
+        distribution_amount
+    );
+    assert_eq!(
+        token_balance(&mut banks_client, token_faucet.vault).await,
+        9 * distribution_amount
+    );
+    assert_eq!(
+        token_balance(&mut banks_client, token_faucet.admin_token_account).await,
+        10 * distribution_amount
+    );
+
+    // Closing hands the vault tokens and the vault rent back to the admin
+    let admin = token_faucet.admin_keypair.pubkey();
+    let admin_lamports = banks_client.get_balance(admin).await.unwrap();
+    let faucet_lamports = banks_client.get_balance(token_faucet.faucet).await.unwrap();
+    let vault_lamports = banks_client.get_balance(token_faucet.vault).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::close_token_vault_faucet(
+            &token_faucet.program_id,
+            &token_faucet.faucet,
+            &admin,
+            &admin,
+            &token_faucet.mint,
+            &token_faucet.token_program,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut banks_client, token_faucet.admin_token_account).await,
+        19 * distribution_amount
+    );
+    assert!(banks_client
+        .get_account(token_faucet.vault)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        banks_client.get_balance(admin).await.unwrap(),
+        admin_lamports + faucet_lamports + vault_lamports
+    );
+}
+
+#[tokio::test]
+async fn test_token_vault_rejects_bad_requests() {
+    let distribution_amount = 1_000;
+    let (program_test, token_faucet) =
+        token_program_test(distribution_amount, FaucetMode::TokenVault, spl_token::id());
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // The token program has to own the mint
+    let mut instruction = initialize_instruction(&token_faucet, distribution_amount);
+    instruction.accounts[5] = AccountMeta::new_readonly(spl_token_2022::id(), false);
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidMint,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[initialize_instruction(&token_faucet, distribution_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient = Pubkey::new_unique();
+
+    // The vault is still empty
+    let mut transaction = Transaction::new_with_payer(
+        &[request_tokens_instruction(
+            &token_faucet,
+            &recipient,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InsufficientFunds,
+    );
+
+    // Only the mint recorded at initialization is accepted
+    let mut wrong_mint_instruction =
+        request_tokens_instruction(&token_faucet, &recipient, &payer.pubkey());
+    wrong_mint_instruction.accounts[5] = AccountMeta::new_readonly(Pubkey::new_unique(), false);
+    let mut transaction =
+        Transaction::new_with_payer(&[wrong_mint_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidMint,
+    );
+
+    // Native-sized account lists are rejected for token faucets
+    let mut instruction = request_tokens_instruction(&token_faucet, &recipient, &payer.pubkey());
+    instruction.accounts.truncate(5);
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::IncorrectAccountCount,
+    );
+}
+
+#[tokio::test]
+async fn test_mint_authority_mode() {
+    mint_authority_mode(spl_token::id()).await;
+}
+
+#[tokio::test]
+async fn test_token_2022_mint_authority_mode() {
+    mint_authority_mode(spl_token_2022::id()).await;
+}
+
+async fn mint_authority_mode(token_program: Pubkey) {
+    let distribution_amount = 7_000;
+    let (program_test, token_faucet) =
+        token_program_test(0, FaucetMode::MintAuthority, token_program);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[initialize_mint_authority_instruction(
+            &token_faucet,
+            distribution_amount,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Each claim mints fresh tokens to the recipient
+    let recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[request_minted_tokens_instruction(
+            &token_faucet,
+            &recipient,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_token_account = get_associated_token_address_with_program_id(
+        &recipient,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    );
+    assert_eq!(
+        token_balance(&mut banks_client, recipient_token_account).await,
+        distribution_amount
+    );
+    let mint_account = banks_client
+        .get_account(token_faucet.mint)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        StateWithExtensions::<Mint>::unpack(&mint_account.data)
+            .unwrap()
+            .base
+            .supply,
+        distribution_amount
+    );
+
+    // There is no vault to replenish
+    let mut transaction = Transaction::new_with_payer(
+        &[replenish_instruction(&token_faucet, 1)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::UnsupportedFaucetMode,
+    );
+
+    // Only the admin can hand the mint authority back
+    let attacker_keypair = Keypair::new();
+    let mut instruction = set_mint_authority_instruction(&token_faucet, attacker_keypair.pubkey());
+    instruction.accounts[1] = AccountMeta::new_readonly(attacker_keypair.pubkey(), true);
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &attacker_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::NotAdmin,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_mint_authority_instruction(
+            &token_faucet,
+            token_faucet.admin_keypair.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mint_account = banks_client
+        .get_account(token_faucet.mint)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        StateWithExtensions::<Mint>::unpack(&mint_account.data)
+            .unwrap()
+            .base
+            .mint_authority,
+        COption::Some(token_faucet.admin_keypair.pubkey())
+    );
+
+    // Without the mint authority the faucet stops minting
+    let mut transaction = Transaction::new_with_payer(
+        &[request_minted_tokens_instruction(
+            &token_faucet,
+            &Pubkey::new_unique(),
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidMintAuthority,
+    );
+}
+
+#[tokio::test]
+async fn test_mint_authority_mode_requires_faucet_authority() {
+    // The mint authority is still the admin
+    let (program_test, token_faucet) =
+        token_program_test(0, FaucetMode::TokenVault, spl_token::id());
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[initialize_mint_authority_instruction(&token_faucet, 1_000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidMintAuthority,
+    );
+}
+
+#[tokio::test]
+async fn test_close_token_vault_faucet_to_new_wallet() {
+    let distribution_amount = 1_000;
+    let (program_test, token_faucet) = token_program_test(
+        10 * distribution_amount,
+        FaucetMode::TokenVault,
+        spl_token::id(),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            initialize_instruction(&token_faucet, distribution_amount),
+            replenish_instruction(&token_faucet, 10 * distribution_amount),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let destination = Pubkey::new_unique();
+    let destination_token_account = get_associated_token_address_with_program_id(
+        &destination,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    );
+    let close = instruction::close_token_vault_faucet(
+        &token_faucet.program_id,
+        &token_faucet.faucet,
+        &token_faucet.admin_keypair.pubkey(),
+        &destination,
+        &token_faucet.mint,
+        &token_faucet.token_program,
+    );
+
+    // The destination has no token account to receive the vault tokens
+    let mut transaction =
+        Transaction::new_with_payer(std::slice::from_ref(&close), Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Creating it in the same transaction, as the client does, lets the close through
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            create_associated_token_account_idempotent(
+                &payer.pubkey(),
+                &destination,
+                &token_faucet.mint,
+                &token_faucet.token_program,
+            ),
+            close,
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut banks_client, destination_token_account).await,
+        10 * distribution_amount
+    );
+    assert!(banks_client
+        .get_account(token_faucet.vault)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_close_mint_authority_faucet() {
+    let (program_test, token_faucet) =
+        token_program_test(0, FaucetMode::MintAuthority, spl_token::id());
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[initialize_mint_authority_instruction(&token_faucet, 1_000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The faucet PDA can't sign once closed, the mint authority moves to the destination
+    let destination = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::close_mint_authority_faucet(
+            &token_faucet.program_id,
+            &token_faucet.faucet,
+            &token_faucet.admin_keypair.pubkey(),
+            &destination,
+            &token_faucet.mint,
+            &token_faucet.token_program,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert!(banks_client
+        .get_account(token_faucet.faucet)
+        .await
+        .unwrap()
+        .is_none());
+    let mint_account = banks_client
+        .get_account(token_faucet.mint)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        StateWithExtensions::<Mint>::unpack(&mint_account.data)
+            .unwrap()
+            .base
+            .mint_authority,
+        COption::Some(destination)
+    );
+}
+
+#[tokio::test]
+async fn test_token_2022_transfer_fee_and_memo() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "simple_token_faucet_token_2022",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin_keypair = Keypair::new();
+    let mint_keypair = Keypair::new();
+    let token_program = spl_token_2022::id();
+    let (faucet, _) = find_faucet_address(&program_id, &admin_keypair.pubkey(), FAUCET_NAME);
+    let mint = mint_keypair.pubkey();
+    let token_faucet = TokenFaucet {
+        program_id,
+        faucet,
+        mint,
+        vault: get_associated_token_address_with_program_id(&faucet, &mint, &token_program),
+        admin_token_account: get_associated_token_address_with_program_id(
+            &admin_keypair.pubkey(),
+            &mint,
+            &token_program,
+        ),
+        token_program,
+        admin_keypair,
+    };
+
+    program_test.add_account(
+        token_faucet.admin_keypair.pubkey(),
+        Account {
+            lamports: 100_000_000,
+            ..Account::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // A 1% transfer fee mint, the admin holds the whole supply
+    let mint_len =
+        ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig])
+            .unwrap();
+    let admin = token_faucet.admin_keypair.pubkey();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint,
+                Rent::default().minimum_balance(mint_len),
+                mint_len as u64,
+                &token_program,
+            ),
+            initialize_transfer_fee_config(&token_program, &mint, None, None, 100, u64::MAX)
+                .unwrap(),
+            initialize_mint2(&token_program, &mint, &admin, None, DECIMALS).unwrap(),
+            create_associated_token_account(&payer.pubkey(), &admin, &mint, &token_program),
+            mint_to(
+                &token_program,
+                &mint,
+                &token_faucet.admin_token_account,
+                &admin,
+                &[],
+                1_000_000,
+            )
+            .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &mint_keypair, &token_faucet.admin_keypair],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let distribution_amount = 10_000;
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            initialize_instruction(&token_faucet, distribution_amount),
+            replenish_instruction(&token_faucet, 500_000),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &token_faucet.admin_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The fee on the deposit is withheld in the vault
+    let vault_balance = token_balance(&mut banks_client, token_faucet.vault).await;
+    assert_eq!(vault_balance, 495_000);
+
+    // The faucet adds the fee on top so the recipient nets the configured amount
+    let recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[request_tokens_instruction(
+            &token_faucet,
+            &recipient,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_token_account =
+        get_associated_token_address_with_program_id(&recipient, &mint, &token_program);
+    assert_eq!(
+        token_balance(&mut banks_client, recipient_token_account).await,
+        distribution_amount
+    );
+    assert_eq!(
+        token_balance(&mut banks_client, token_faucet.vault).await,
+        vault_balance - 10_102
+    );
+
+    // A recipient whose token account requires memos
+    let memo_keypair = Keypair::new();
+    let memo_token_account =
+        get_associated_token_address_with_program_id(&memo_keypair.pubkey(), &mint, &token_program);
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            create_associated_token_account(
+                &payer.pubkey(),
+                &memo_keypair.pubkey(),
+                &mint,
+                &token_program,
+            ),
+            reallocate(
+                &token_program,
+                &memo_token_account,
+                &payer.pubkey(),
+                &memo_keypair.pubkey(),
+                &[],
+                &[ExtensionType::MemoTransfer],
+            )
+            .unwrap(),
+            enable_required_transfer_memos(
+                &token_program,
+                &memo_token_account,
+                &memo_keypair.pubkey(),
+                &[],
+            )
+            .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &memo_keypair], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut without_memo_instruction =
+        request_tokens_instruction(&token_faucet, &memo_keypair.pubkey(), &payer.pubkey());
+    without_memo_instruction.accounts.pop();
+    let mut transaction =
+        Transaction::new_with_payer(&[without_memo_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_faucet_error(
+        banks_client.process_transaction(transaction).await,
+        FaucetError::InvalidMemoProgram,
+    );
+
+    // Passing the memo program lets the faucet log a memo before the transfer
+    let mut transaction = Transaction::new_with_payer(
+        &[request_tokens_instruction(
+            &token_faucet,
+            &memo_keypair.pubkey(),
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut banks_client, memo_token_account).await,
+        distribution_amount
+    );
+}